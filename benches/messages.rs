@@ -0,0 +1,69 @@
+//! Measures the cost of creating errors with literal and formatted messages.
+//!
+//! Run with `cargo bench --bench messages`. A literal message is stored as a `&'static str`,
+//! so creating the error only allocates the error and its frames, where formatting the same
+//! message into a `String` costs one more allocation:
+//!
+//! ```text
+//! literal    error!("connection refused")              2 allocs/iter
+//! formatted  error!("connection refused: {}", port)    3 allocs/iter
+//! owned      Error::new(Some(String::from(...)), ...)  3 allocs/iter
+//! ```
+//!
+//! Without `--bench`, as under `cargo test`, every case runs once.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use fu::{error, Error, Location};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+fn bench(name: &str, iterations: u32, f: impl Fn() -> Error) {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for _ in 0..iterations {
+        black_box(f());
+    }
+    let elapsed = start.elapsed() / iterations;
+    let allocations = (ALLOCATIONS.load(Ordering::Relaxed) - allocations) / iterations as usize;
+    println!("{name:<10} {elapsed:>10.2?}/iter {allocations:>3} allocs/iter");
+}
+
+fn main() {
+    let iterations = if std::env::args().any(|arg| arg == "--bench") {
+        1_000_000
+    } else {
+        1
+    };
+    let port = black_box(8080);
+
+    bench("literal", iterations, || error!("connection refused"));
+    bench("formatted", iterations, || {
+        error!("connection refused: {}", port)
+    });
+    bench("owned", iterations, || {
+        Error::new(
+            Some(String::from("connection refused")),
+            Location::new(file!(), line!(), column!()),
+        )
+    });
+}
@@ -16,4 +16,4 @@ fn main() -> Result<()> {
     example(-1)
 }
 
-// Error: value must be non-negative    examples/foo.rs:[4:5]
+// Error: value must be non-negative    examples/foo.rs:[6:5] in foo::example
@@ -1,7 +1,7 @@
-use fu::Result;
+use fu::{Result, Wrap};
 
 fn example() -> Result<()> {
-    let _ = std::fs::File::open("abc")?;
+    let _ = std::fs::File::open("abc").wrap("failed to open abc")?;
     Ok(())
 }
 
@@ -0,0 +1,219 @@
+//! Derive macro for typed errors that convert into a [`fu::Error`] with the location of the
+//! conversion.
+//!
+//! Use it through the `derive` feature of `fu`, which re-exports [`FuError`].
+//!
+//! [`fu::Error`]: https://docs.rs/fu/latest/fu/struct.Error.html
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Fields, Ident, LitStr};
+
+/// Derives `Display`, `std::error::Error` and `From<T> for fu::Error`.
+///
+/// Every variant, or the struct itself, takes an `#[error("...")]` attribute with its message.
+/// The message can refer to named fields as `{name}` and to tuple fields as `{0}`, and may be
+/// followed by additional format arguments. A field marked `#[source]`, or named `source`, is
+/// returned by `Error::source`. It can be an error or a boxed `dyn Error`.
+///
+/// Converting into a `fu::Error`, e.g. with `?`, records the location of the conversion.
+///
+/// # Example
+/// ```
+/// use fu::FuError;
+///
+/// #[derive(Debug, FuError)]
+/// enum ConfigError {
+///     #[error("config file {path} not found")]
+///     NotFound { path: String },
+///     #[error("invalid port {0}")]
+///     InvalidPort(u16),
+///     #[error("failed to read config")]
+///     Io(#[source] std::io::Error),
+///     #[error("failed to parse config")]
+///     Parse {
+///         #[source]
+///         cause: Box<dyn std::error::Error + Send + Sync>,
+///     },
+/// }
+///
+/// fn load() -> fu::Result<()> {
+///     Err(ConfigError::InvalidPort(0))?
+/// }
+///
+/// let err = load().unwrap_err();
+/// assert!(err.is::<ConfigError>());
+/// # #[cfg(not(fu_no_location))]
+/// assert_eq!(err.location().file(), file!());
+/// assert!(err.to_string().starts_with("invalid port 0"));
+///
+/// # use std::error::Error as _;
+/// let io = ConfigError::Io(std::io::Error::other("disk on fire"));
+/// assert_eq!(io.source().unwrap().to_string(), "disk on fire");
+///
+/// let parse = ConfigError::Parse { cause: "unexpected `}`".into() };
+/// assert_eq!(parse.source().unwrap().to_string(), "unexpected `}`");
+/// ```
+#[proc_macro_derive(FuError, attributes(error, source))]
+pub fn derive_fu_error(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (fmt_arms, source_arms) = match &input.data {
+        Data::Enum(data) => {
+            let mut fmt_arms = Vec::new();
+            let mut source_arms = Vec::new();
+            for variant in &data.variants {
+                let ident = &variant.ident;
+                let path = quote!(#name::#ident);
+                let (fmt, source) = expand_fields(&path, &variant.fields, &variant.attrs, ident)?;
+                fmt_arms.push(fmt);
+                source_arms.extend(source);
+            }
+            (fmt_arms, source_arms)
+        }
+        Data::Struct(data) => {
+            let (fmt, source) = expand_fields(&quote!(#name), &data.fields, &input.attrs, name)?;
+            (vec![fmt], source.into_iter().collect())
+        }
+        Data::Union(_) => {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                "`FuError` cannot be derived for unions",
+            ))
+        }
+    };
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let from_predicates = where_clause.map(|w| &w.predicates);
+    Ok(quote! {
+        impl #impl_generics ::core::fmt::Display for #name #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                match self {
+                    #(#fmt_arms)*
+                }
+            }
+        }
+
+        impl #impl_generics ::core::error::Error for #name #ty_generics
+        where
+            Self: ::core::fmt::Debug,
+            #from_predicates
+        {
+            #[allow(unused_variables)]
+            fn source(&self) -> ::core::option::Option<&(dyn ::core::error::Error + 'static)> {
+                #[allow(unreachable_patterns)]
+                match self {
+                    #(#source_arms)*
+                    _ => ::core::option::Option::None,
+                }
+            }
+        }
+
+        impl #impl_generics ::core::convert::From<#name #ty_generics> for ::fu::Error
+        where
+            #name #ty_generics: ::core::error::Error + Send + Sync + 'static,
+            #from_predicates
+        {
+            #[track_caller]
+            fn from(err: #name #ty_generics) -> Self {
+                ::fu::__private::error_chained(err, ::fu::Location::caller())
+            }
+        }
+    })
+}
+
+/// Builds the `Display` match arm of a variant, and its `source` match arm if it has one.
+fn expand_fields(
+    path: &TokenStream2,
+    fields: &Fields,
+    attrs: &[Attribute],
+    ident: &Ident,
+) -> syn::Result<(TokenStream2, Option<TokenStream2>)> {
+    let message = error_attr(attrs, ident)?;
+    let bindings: Vec<Ident> = fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| match &field.ident {
+            Some(ident) => ident.clone(),
+            None => format_ident!("_{}", i),
+        })
+        .collect();
+    let pattern = match fields {
+        Fields::Named(_) => quote!(#path { #(#bindings),* }),
+        Fields::Unnamed(_) => quote!(#path ( #(#bindings),* )),
+        Fields::Unit => quote!(#path),
+    };
+
+    let fmt = quote!(#pattern => ::core::write!(f, #message),);
+    let source = fields
+        .iter()
+        .zip(&bindings)
+        .find(|(field, _)| {
+            field
+                .attrs
+                .iter()
+                .any(|attr| attr.path().is_ident("source"))
+                || field.ident.as_ref().is_some_and(|ident| ident == "source")
+        })
+        .map(|(_, binding)| {
+            // Method syntax derefs boxed trait objects, which are not errors themselves.
+            quote! {
+                #pattern => {
+                    use ::fu::__private::AsDynError as _;
+                    ::core::option::Option::Some(#binding.as_dyn_error())
+                }
+            }
+        });
+    Ok((fmt, source))
+}
+
+/// Parses `#[error("...", args...)]`, rewriting `{0}` into `{_0}` to refer to tuple fields.
+fn error_attr(attrs: &[Attribute], ident: &Ident) -> syn::Result<TokenStream2> {
+    let attr = attrs
+        .iter()
+        .find(|attr| attr.path().is_ident("error"))
+        .ok_or_else(|| syn::Error::new(ident.span(), "missing `#[error(\"...\")]` attribute"))?;
+    attr.parse_args_with(|input: syn::parse::ParseStream<'_>| {
+        let message: LitStr = input.parse()?;
+        let rest: TokenStream2 = input.parse()?;
+        let message = LitStr::new(&rewrite_positional(&message.value()), message.span());
+        Ok(quote!(#message #rest))
+    })
+}
+
+fn rewrite_positional(message: &str) -> String {
+    let mut out = String::with_capacity(message.len());
+    let mut chars = message.chars().peekable();
+    while let Some(c) = chars.next() {
+        out.push(c);
+        if c != '{' {
+            continue;
+        }
+        if chars.peek() == Some(&'{') {
+            out.push(chars.next().unwrap());
+        } else if chars.peek().is_some_and(char::is_ascii_digit) {
+            out.push('_');
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrite_positional() {
+        assert_eq!(rewrite_positional("port {0}"), "port {_0}");
+        assert_eq!(rewrite_positional("{1:?} and {name}"), "{_1:?} and {name}");
+        assert_eq!(rewrite_positional("{{0}} {}"), "{{0}} {}");
+    }
+}
@@ -0,0 +1,47 @@
+//! [`actix_web`] support: [`Error`] as a [`ResponseError`].
+
+use actix_web::http::{header, StatusCode};
+use actix_web::{HttpResponse, ResponseError};
+
+use crate::{web, Error};
+
+/// Responds with the [status](Error::with_status) of the error, or `500`, and a body in the
+/// [global format](crate::set_body_format).
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// use actix_web::ResponseError;
+///
+/// let err = error!("user not found").with_status(404);
+/// assert_eq!(err.status_code(), 404);
+/// ```
+impl ResponseError for Error {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::from_u16(web::status_code(self)).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let (content_type, body) = web::body(self);
+        HttpResponse::build(ResponseError::status_code(self))
+            .insert_header((header::CONTENT_TYPE, content_type))
+            .body(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::ResponseError;
+
+    use crate::*;
+
+    #[test]
+    fn test_error_response() {
+        let response = error!("oops").with_status(409).error_response();
+        assert_eq!(response.status(), 409);
+        let content_type = response
+            .headers()
+            .get(actix_web::http::header::CONTENT_TYPE);
+        assert_eq!(content_type.unwrap(), "application/json");
+    }
+}
@@ -0,0 +1,43 @@
+//! [`axum`](https://docs.rs/axum) support: [`Error`] as a response.
+
+use axum_core::response::{IntoResponse, Response};
+
+use crate::{web, Error};
+
+/// Responds with the [status](Error::with_status) of the error, or `500`, and a body in the
+/// [global format](crate::set_body_format).
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// use axum_core::response::IntoResponse;
+///
+/// let response = error!("user not found").with_status(404).into_response();
+/// assert_eq!(response.status(), 404);
+/// ```
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        let status = http::StatusCode::from_u16(web::status_code(&self))
+            .unwrap_or(http::StatusCode::INTERNAL_SERVER_ERROR);
+        let (content_type, body) = web::body(&self);
+        let headers = [(
+            http::header::CONTENT_TYPE,
+            http::HeaderValue::from_static(content_type),
+        )];
+        (status, headers, body).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum_core::response::IntoResponse;
+
+    use crate::*;
+
+    #[test]
+    fn test_into_response() {
+        let response = error!("oops").into_response();
+        assert_eq!(response.status(), 500);
+        assert_eq!(response.headers()["content-type"], "application/json");
+    }
+}
@@ -0,0 +1,158 @@
+//! [`ErrorBuilder`], to construct an [`Error`] with many attributes in one chain.
+
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::string::String;
+use core::error::Error as StdError;
+
+use crate::{Error, ErrorKind, Location, Severity};
+
+type Source = Box<dyn FnOnce(Error) -> Error + Send + Sync>;
+
+/// Builds an [`Error`], see [`Error::builder`].
+///
+/// The error is created, and reported to the [error hook](crate::set_error_hook), by
+/// [`build`](ErrorBuilder::build) once every attribute is set.
+#[must_use]
+pub struct ErrorBuilder {
+    err: Error,
+    source: Option<Source>,
+}
+
+impl ErrorBuilder {
+    #[cfg_attr(not(fu_no_location), track_caller)]
+    pub(crate) fn new() -> Self {
+        ErrorBuilder {
+            err: Error::new_unreported(None, Location::caller()),
+            source: None,
+        }
+    }
+
+    /// Sets the message of the error.
+    pub fn message<M: Into<Cow<'static, str>>>(mut self, message: M) -> Self {
+        self.err.head_mut().message = Some(message.into());
+        self
+    }
+
+    /// Sets the location of the error, instead of the caller of [`Error::builder`].
+    pub fn location<L: Into<Location>>(mut self, location: L) -> Self {
+        self.err.head_mut().location = location.into();
+        self
+    }
+
+    /// Sets the source of the error, see [`Error::chain`].
+    pub fn source<E>(mut self, source: E) -> Self
+    where
+        E: StdError + Send + Sync + 'static,
+    {
+        self.source = Some(Box::new(move |err: Error| err.chain(source)));
+        self
+    }
+
+    /// Sets the code of the error, see [`Error::with_code`].
+    pub fn code<C: Into<String>>(mut self, code: C) -> Self {
+        self.err = self.err.with_code(code);
+        self
+    }
+
+    /// Sets the kind of the error, see [`Error::with_kind`].
+    pub fn kind(mut self, kind: ErrorKind) -> Self {
+        self.err = self.err.with_kind(kind);
+        self
+    }
+
+    /// Sets the severity of the error, see [`Error::with_severity`].
+    pub fn severity(mut self, severity: Severity) -> Self {
+        self.err = self.err.with_severity(severity);
+        self
+    }
+
+    /// Adds a help message to the error, see [`Error::help`].
+    pub fn help<H: Into<String>>(mut self, help: H) -> Self {
+        self.err = self.err.help(help);
+        self
+    }
+
+    /// Sets the documentation URL of the error, see [`Error::see`].
+    pub fn see<U: Into<String>>(mut self, url: U) -> Self {
+        self.err = self.err.see(url);
+        self
+    }
+
+    /// Adds a note to the error, see [`Error::note`].
+    pub fn note<N: Into<String>>(mut self, note: N) -> Self {
+        self.err = self.err.note(note);
+        self
+    }
+
+    /// Adds a key-value field to the error, see [`Error::with`].
+    pub fn field<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: core::fmt::Display,
+    {
+        self.err = self.err.with(key, value);
+        self
+    }
+
+    /// Sets the HTTP status of the error, see [`Error::with_status`].
+    pub fn status(mut self, status: u16) -> Self {
+        self.err = self.err.with_status(status);
+        self
+    }
+
+    /// Sets the exit code of the error, see [`Error::with_exit_code`].
+    pub fn exit_code(mut self, code: u8) -> Self {
+        self.err = self.err.with_exit_code(code);
+        self
+    }
+
+    /// Creates the error.
+    pub fn build(self) -> Error {
+        let mut err = match self.source {
+            Some(source) => source(self.err),
+            None => self.err,
+        };
+        // Attaching the source already reported the error, with every attribute set.
+        err.created();
+        err
+    }
+}
+
+#[cfg(all(test, not(fu_no_location)))]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_builder() {
+        let line = line!() + 1;
+        let err = Error::builder()
+            .message("user not found")
+            .code("E404")
+            .kind(ErrorKind::NotFound)
+            .help("check the user id")
+            .field("user_id", 42)
+            .status(404)
+            .source(std::io::Error::other("no rows"))
+            .build();
+
+        assert_eq!(err.message(), Some("user not found"));
+        assert_eq!(err.code(), Some("E404"));
+        assert_eq!(err.kind(), Some(ErrorKind::NotFound));
+        assert_eq!(err.helps().collect::<Vec<_>>(), ["check the user id"]);
+        assert_eq!(err.fields().collect::<Vec<_>>(), [("user_id", "42")]);
+        assert_eq!(err.status(), Some(404));
+        assert!(err.is::<std::io::Error>());
+        assert_eq!(err.location().file(), file!());
+        assert_eq!(err.location().line(), line);
+
+        let err = Error::builder()
+            .location(Location::new("src/main.rs", 3, 1))
+            .source(error!("inner").with_code("E1"))
+            .build();
+        assert_eq!(err.message(), None);
+        assert_eq!(err.frames().len(), 2);
+        assert_eq!(err.location().file(), "src/main.rs");
+        assert_eq!(err.code(), Some("E1"));
+    }
+}
@@ -0,0 +1,86 @@
+//! Conversions between [`Error`] and [`eyre::Report`].
+//!
+//! An [`Error`] already converts into an [`eyre::Report`] like any other error, keeping its
+//! frames, and converts back losslessly as long as no context was added on the `eyre` side.
+//! The report handler renders the error with `Display`, so its locations are shown on the
+//! `eyre` side as well.
+
+use core::error::Error as StdError;
+
+use crate::{Error, Location};
+
+/// An [`eyre::Report`] used as the source of an [`Error`], exposing its chain.
+struct EyreReport(eyre::Report);
+
+impl core::fmt::Display for EyreReport {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl core::fmt::Debug for EyreReport {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl StdError for EyreReport {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.0.source()
+    }
+}
+
+/// Converts an [`eyre::Report`], keeping its chain as the source of the new error.
+///
+/// If it wraps an [`Error`] without further context, that error is given back unchanged.
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// let err = eyre::eyre!("connection refused").wrap_err("failed to fetch");
+/// let err = Error::from(err);
+/// assert!(err.to_string().starts_with("failed to fetch"));
+/// assert!(err.to_string().contains("Caused by: connection refused"));
+///
+/// let err = Error::from(eyre::Report::from(error!("oops")));
+/// assert_eq!(err.message(), Some("oops"));
+/// ```
+impl From<eyre::Report> for Error {
+    #[cfg_attr(not(fu_no_location), track_caller)]
+    fn from(err: eyre::Report) -> Self {
+        // `eyre::Report::downcast` would also look through context and drop it.
+        let outer: &(dyn StdError + 'static) = &*err;
+        if outer.is::<Error>() {
+            return err
+                .downcast()
+                .expect("the error was just checked to be an `Error`");
+        }
+        Error::new_unreported(None, Location::caller()).chain(EyreReport(err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_eyre_round_trip() {
+        let err = error!("inner").context("outer").with_code("E1");
+        let any = ::eyre::Report::from(err);
+        assert_eq!(
+            any.downcast_ref::<Error>().unwrap().message(),
+            Some("outer")
+        );
+
+        let err = Error::from(any);
+        assert_eq!(err.inner.frames.len(), 2);
+        assert_eq!(err.code(), Some("E1"));
+
+        // Context added on the `eyre` side is kept as the source.
+        let err = Error::from(::eyre::Report::from(err).wrap_err("with eyre"));
+        assert_eq!(err.message(), None);
+        assert!(err.inner.source.is_some());
+        let sources = std::iter::successors(err.source(), |&e| e.source()).count();
+        assert_eq!(sources, 2);
+    }
+}
@@ -0,0 +1,141 @@
+//! A C interface to inspect errors returned across an FFI boundary.
+//!
+//! A library hands out an error as an opaque `fu_error` pointer with [`ErrorHandle::into_raw`],
+//! which C callers inspect and release with the `extern "C"` functions of this module:
+//!
+//! ```c
+//! typedef struct fu_error fu_error;
+//!
+//! const char *fu_error_message(const fu_error *err);
+//! const char *fu_error_file(const fu_error *err);
+//! uint32_t fu_error_line(const fu_error *err);
+//! void fu_error_free(fu_error *err);
+//! ```
+
+use std::ffi::{c_char, CString};
+
+use crate::Error;
+
+/// An [`Error`] owned by C code, along with its strings as C strings.
+pub struct ErrorHandle {
+    error: Error,
+    message: CString,
+    file: CString,
+}
+
+impl ErrorHandle {
+    /// Moves the error to the heap and returns a pointer to pass to C code, which must release
+    /// it with [`fu_error_free`].
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// #[no_mangle]
+    /// pub extern "C" fn parse_port(input: *const core::ffi::c_char, port: *mut u16) -> *mut ffi::ErrorHandle {
+    ///     let input = unsafe { core::ffi::CStr::from_ptr(input) };
+    ///     match input.to_str().wrap("invalid utf-8").and_then(|s| s.parse().wrap("invalid port")) {
+    ///         Ok(n) => {
+    ///             unsafe { *port = n };
+    ///             core::ptr::null_mut()
+    ///         }
+    ///         Err(err) => ffi::ErrorHandle::into_raw(err),
+    ///     }
+    /// }
+    /// ```
+    pub fn into_raw(error: Error) -> *mut ErrorHandle {
+        let handle = ErrorHandle {
+            message: c_string(&error.compact().to_string()),
+            file: c_string(error.location().file()),
+            error,
+        };
+        Box::into_raw(Box::new(handle))
+    }
+
+    /// Returns the error.
+    pub fn error(&self) -> &Error {
+        &self.error
+    }
+}
+
+/// Returns a C string of `s`, without the nul bytes it may contain.
+fn c_string(s: &str) -> CString {
+    let bytes: Vec<u8> = s.bytes().filter(|&b| b != 0).collect();
+    CString::new(bytes).expect("nul bytes are removed")
+}
+
+/// Returns the messages of the chain of the error, see [`Error::compact`], or null if `err` is
+/// null.
+///
+/// # Safety
+/// `err` must be null or a pointer returned by [`ErrorHandle::into_raw`] that was not freed. The
+/// string is valid until the error is freed.
+#[no_mangle]
+pub unsafe extern "C" fn fu_error_message(err: *const ErrorHandle) -> *const c_char {
+    match unsafe { err.as_ref() } {
+        Some(handle) => handle.message.as_ptr(),
+        None => core::ptr::null(),
+    }
+}
+
+/// Returns the file the error was created in, or null if `err` is null.
+///
+/// # Safety
+/// Same as [`fu_error_message`].
+#[no_mangle]
+pub unsafe extern "C" fn fu_error_file(err: *const ErrorHandle) -> *const c_char {
+    match unsafe { err.as_ref() } {
+        Some(handle) => handle.file.as_ptr(),
+        None => core::ptr::null(),
+    }
+}
+
+/// Returns the line the error was created at, or 0 if `err` is null.
+///
+/// # Safety
+/// `err` must be null or a pointer returned by [`ErrorHandle::into_raw`] that was not freed.
+#[no_mangle]
+pub unsafe extern "C" fn fu_error_line(err: *const ErrorHandle) -> u32 {
+    match unsafe { err.as_ref() } {
+        Some(handle) => handle.error.location().line(),
+        None => 0,
+    }
+}
+
+/// Frees the error. Does nothing if `err` is null.
+///
+/// # Safety
+/// `err` must be null or a pointer returned by [`ErrorHandle::into_raw`] that was not freed.
+#[no_mangle]
+pub unsafe extern "C" fn fu_error_free(err: *mut ErrorHandle) {
+    if !err.is_null() {
+        drop(unsafe { Box::from_raw(err) });
+    }
+}
+
+#[cfg(all(test, not(fu_no_location)))]
+mod tests {
+    use std::ffi::CStr;
+
+    use super::*;
+    use crate::*;
+
+    #[test]
+    fn test_ffi() {
+        let line = line!() + 1;
+        let err = ErrorHandle::into_raw(error!("no\0pe").context("failed"));
+        unsafe {
+            let message = CStr::from_ptr(fu_error_message(err));
+            assert_eq!(message.to_str().unwrap(), "failed: nope");
+            assert_eq!(
+                CStr::from_ptr(fu_error_file(err)).to_str().unwrap(),
+                file!()
+            );
+            assert_eq!(fu_error_line(err), line);
+            fu_error_free(err);
+
+            assert!(fu_error_message(core::ptr::null()).is_null());
+            assert_eq!(fu_error_line(core::ptr::null()), 0);
+            fu_error_free(core::ptr::null_mut());
+        }
+    }
+}
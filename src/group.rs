@@ -0,0 +1,330 @@
+//! [`ErrorGroup`], an aggregate of several independent errors.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::error::Error as StdError;
+use core::fmt::Write;
+
+use crate::render::Indented;
+use crate::{Error, Location};
+
+/// A collection of independent [`Error`]s, each keeping its own location and chain.
+///
+/// Batch jobs and validators can report every failure instead of only the first one. The group
+/// is rendered as a numbered list, and converts into an [`Error`] with `?`.
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// fu::set_color(false);
+/// let mut errors = ErrorGroup::new();
+/// errors.push(error!("name is empty"));
+/// errors.push(error!("age is negative"));
+///
+/// let rendered = errors.to_string();
+/// assert!(rendered.starts_with("2 errors occurred:\n  1. name is empty"));
+/// assert!(rendered.contains("\n  2. age is negative"));
+/// ```
+#[derive(Clone, Default)]
+pub struct ErrorGroup {
+    errors: Vec<Error>,
+}
+
+impl ErrorGroup {
+    /// Creates an empty group.
+    pub fn new() -> Self {
+        ErrorGroup { errors: Vec::new() }
+    }
+
+    /// Adds an error to the group.
+    pub fn push(&mut self, err: Error) {
+        self.errors.push(err);
+    }
+
+    /// Returns the number of errors in the group.
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    /// Returns true if the group holds no errors.
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Returns an iterator over the errors of the group.
+    pub fn iter(&self) -> core::slice::Iter<'_, Error> {
+        self.errors.iter()
+    }
+
+    /// Returns the errors of the group.
+    pub fn into_errors(self) -> Vec<Error> {
+        self.errors
+    }
+
+    /// Returns `Ok(())` if the group is empty, and the group itself otherwise.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// fn validate(name: &str) -> core::result::Result<(), ErrorGroup> {
+    ///     let mut errors = ErrorGroup::new();
+    ///     if name.is_empty() {
+    ///         errors.push(error!("name is empty"));
+    ///     }
+    ///     errors.into_result()
+    /// }
+    ///
+    /// assert!(validate("fu").is_ok());
+    /// assert_eq!(validate("").unwrap_err().len(), 1);
+    /// ```
+    pub fn into_result(self) -> core::result::Result<(), Self> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl From<Vec<Error>> for ErrorGroup {
+    fn from(errors: Vec<Error>) -> Self {
+        ErrorGroup { errors }
+    }
+}
+
+impl FromIterator<Error> for ErrorGroup {
+    fn from_iter<I: IntoIterator<Item = Error>>(iter: I) -> Self {
+        ErrorGroup {
+            errors: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl Extend<Error> for ErrorGroup {
+    fn extend<I: IntoIterator<Item = Error>>(&mut self, iter: I) {
+        self.errors.extend(iter);
+    }
+}
+
+impl IntoIterator for ErrorGroup {
+    type Item = Error;
+    type IntoIter = alloc::vec::IntoIter<Error>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.errors.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a ErrorGroup {
+    type Item = &'a Error;
+    type IntoIter = core::slice::Iter<'a, Error>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.errors.iter()
+    }
+}
+
+/// Collects an iterator of results while keeping every error, see [`try_collect_all`].
+///
+/// [`try_collect_all`]: CollectAll::try_collect_all
+pub trait CollectAll<T>: Iterator<Item = crate::Result<T>> + Sized {
+    /// Collects every value, or returns an [`ErrorGroup`] with every error encountered.
+    ///
+    /// Unlike `collect::<Result<_>>()`, iteration does not stop at the first error. The
+    /// position of each failed item in the iterator is attached as an `index`
+    /// [field](Error::with).
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// let inputs = ["1", "x", "3", "y"];
+    /// let group = inputs
+    ///     .iter()
+    ///     .map(|s| s.parse::<i32>().wrap("invalid number"))
+    ///     .try_collect_all::<Vec<_>>()
+    ///     .unwrap_err();
+    ///
+    /// let indices: Vec<_> = group.iter().flat_map(Error::fields).collect();
+    /// assert_eq!(indices, [("index", "1"), ("index", "3")]);
+    /// ```
+    fn try_collect_all<C: FromIterator<T>>(self) -> core::result::Result<C, ErrorGroup> {
+        let mut group = ErrorGroup::new();
+        let values = self
+            .enumerate()
+            .filter_map(|(index, result)| match result {
+                Ok(value) => Some(value),
+                Err(err) => {
+                    group.push(err.with("index", index));
+                    None
+                }
+            })
+            .collect();
+        group.into_result().map(|()| values)
+    }
+}
+
+impl<T, I: Iterator<Item = crate::Result<T>>> CollectAll<T> for I {}
+
+impl ErrorGroup {
+    /// Returns the line heading the errors of the group, e.g. `2 errors occurred:`.
+    pub(crate) fn header(&self) -> String {
+        match self.errors.len() {
+            1 => "1 error occurred:".into(),
+            n => alloc::format!("{} errors occurred:", n),
+        }
+    }
+
+    /// Returns the header followed by the errors of the group on a single line, in their
+    /// [compact](Error::compact) form, e.g. `2 errors occurred: [a; b]`.
+    pub(crate) fn compact_message(&self) -> String {
+        let mut message = self.header();
+        let mut separator = " [";
+        for err in &self.errors {
+            let _ = write!(message, "{}{}", separator, err.compact());
+            separator = "; ";
+        }
+        if !self.errors.is_empty() {
+            message.push(']');
+        }
+        message
+    }
+
+    /// Writes every error of the group on its own numbered line, indented by `indent`, with
+    /// `write_error`. The chain of each error is aligned with its message, after the number.
+    pub(crate) fn write_errors(
+        &self,
+        out: &mut dyn Write,
+        indent: usize,
+        write_error: &mut dyn FnMut(&mut dyn Write, &Error) -> core::fmt::Result,
+    ) -> core::fmt::Result {
+        for (i, err) in self.errors.iter().enumerate() {
+            let number = i + 1;
+            write!(out, "\n{:indent$}  {}. ", "", number, indent = indent)?;
+            let indent = indent + 4 + number.ilog10() as usize + 1;
+            write_error(&mut Indented { out, indent }, err)?;
+        }
+        Ok(())
+    }
+}
+
+/// Renders every error of the group, numbered, with the chain of each aligned below it. The
+/// alternate format (`{:#}`) is passed on to the errors.
+impl core::fmt::Display for ErrorGroup {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(&self.header())?;
+        let alternate = f.alternate();
+        self.write_errors(f, 0, &mut |out, err| {
+            if alternate {
+                write!(out, "{:#}", err)
+            } else {
+                write!(out, "{}", err)
+            }
+        })
+    }
+}
+
+impl core::fmt::Debug for ErrorGroup {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl StdError for ErrorGroup {}
+
+impl From<ErrorGroup> for Error {
+    #[cfg_attr(not(fu_no_location), track_caller)]
+    fn from(group: ErrorGroup) -> Self {
+        Error::new_unreported(None, Location::caller()).chain(group)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[cfg(not(fu_no_location))]
+    #[test]
+    fn test_render_group() {
+        set_color(false);
+        let group: ErrorGroup = (0..10)
+            .map(|i| error!("inner {}", i).context("outer"))
+            .collect();
+        let rendered = group.to_string();
+
+        assert!(rendered.starts_with("10 errors occurred:\n  1. outer    "));
+        assert!(rendered.contains("\n       Caused by: inner 0\n"));
+        assert!(rendered.contains("\n  10. outer    "));
+        assert!(rendered.contains("\n        Caused by: inner 9"));
+    }
+
+    #[test]
+    fn test_group_into_error() {
+        fn validate() -> Result<()> {
+            let group = ErrorGroup::from(vec![error!("a"), error!("b")]);
+            group.into_result()?;
+            Ok(())
+        }
+
+        let err = validate().unwrap_err();
+        assert!(err.is::<ErrorGroup>());
+        assert_eq!(err.downcast_ref::<ErrorGroup>().unwrap().len(), 2);
+    }
+
+    fn validate() -> Result<()> {
+        let group = ErrorGroup::from(vec![error!("name is empty"), error!("age is negative")]);
+        group.into_result().context("invalid user")
+    }
+
+    #[test]
+    fn test_group_in_chain() {
+        let err = validate().unwrap_err();
+        assert_eq!(
+            err.compact().to_string(),
+            "invalid user: 2 errors occurred: [name is empty; age is negative]"
+        );
+        assert_eq!(
+            err.to_snapshot_string(),
+            "invalid user\n  Caused by: 2 errors occurred:\n    1. name is empty\n    2. age is negative"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_group_in_chain_serde() {
+        let json = serde_json::to_value(validate().unwrap_err()).unwrap();
+        assert_eq!(
+            json["chain"][0]["message"],
+            "2 errors occurred: [name is empty; age is negative]"
+        );
+    }
+
+    #[cfg(all(feature = "html", feature = "markdown"))]
+    #[test]
+    fn test_group_in_chain_markup() {
+        let err = validate().unwrap_err();
+        let html = err.html().to_string();
+        assert!(html.contains("<li><span class=\"fu-message\">2 errors occurred: [name is empty; age is negative]</span></li>\n"));
+        let markdown = err.markdown().to_string();
+        assert!(markdown.ends_with("\n\n- 2 errors occurred: \\[name is empty; age is negative\\]"));
+    }
+
+    #[test]
+    fn test_try_collect_all() {
+        let values: Vec<i32> = ["1", "2"]
+            .iter()
+            .map(|s| s.parse::<i32>().wrap("parse"))
+            .try_collect_all()
+            .unwrap();
+        assert_eq!(values, [1, 2]);
+
+        let mut seen = 0;
+        let group = ["x", "2", "y"]
+            .iter()
+            .inspect(|_| seen += 1)
+            .map(|s| s.parse::<i32>().wrap("parse"))
+            .try_collect_all::<Vec<_>>()
+            .unwrap_err();
+        assert_eq!(seen, 3);
+        assert_eq!(group.len(), 2);
+    }
+}
@@ -0,0 +1,122 @@
+//! [`set_error_hook`], observing every [`Error`] when it is created.
+
+use std::cell::Cell;
+use std::sync::{Arc, RwLock};
+
+use crate::Error;
+
+type ErrorHook = dyn Fn(&Error) + Send + Sync;
+
+static ERROR_HOOK: RwLock<Option<Arc<ErrorHook>>> = RwLock::new(None);
+
+thread_local! {
+    /// Whether the current thread is running the hook, to ignore the errors it creates.
+    static RUNNING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Installs a hook called with every [`Error`] when it is created, by a macro, a constructor or
+/// a conversion.
+///
+/// Errors created by conversions are passed once their source is attached, and every error is
+/// passed only once, however many sources are chained to it. Errors created by the hook itself
+/// are not passed to it. Installing a hook replaces the previous one.
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// static ERRORS: AtomicUsize = AtomicUsize::new(0);
+/// fu::set_error_hook(|_| {
+///     ERRORS.fetch_add(1, Ordering::Relaxed);
+/// });
+///
+/// let _ = error!("oops");
+/// let _ = "x".parse::<i32>().wrap("invalid number");
+/// assert_eq!(ERRORS.load(Ordering::Relaxed), 2);
+/// ```
+pub fn set_error_hook<F>(hook: F)
+where
+    F: Fn(&Error) + Send + Sync + 'static,
+{
+    let mut current = ERROR_HOOK.write().unwrap_or_else(|e| e.into_inner());
+    *current = Some(Arc::new(hook));
+}
+
+/// Clears [`RUNNING`] when dropped, also when the hook panics.
+struct Running;
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        RUNNING.set(false);
+    }
+}
+
+/// Calls the installed hook with a newly created error.
+pub(crate) fn call(err: &Error) {
+    let hook = ERROR_HOOK.read().unwrap_or_else(|e| e.into_inner()).clone();
+    if let Some(hook) = hook {
+        if !RUNNING.replace(true) {
+            let _running = Running;
+            hook(err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+    use std::thread::{self, ThreadId};
+
+    use crate::*;
+
+    #[test]
+    fn test_error_hook() {
+        // Other tests create errors concurrently, on their own threads.
+        static SEEN: Mutex<Vec<(ThreadId, String)>> = Mutex::new(Vec::new());
+        set_error_hook(|err| {
+            let _ = error!("created by the hook");
+            let entry = (thread::current().id(), err.compact().to_string());
+            SEEN.lock().unwrap().push(entry);
+            if err.message() == Some("hook panic") {
+                panic!("the hook panicked");
+            }
+        });
+
+        let _ = error!("hook test");
+        let _ = Error::from(std::io::Error::other("hook test"));
+        let _ = error!("hook test").context("outer");
+        let _ = error!();
+        let _ = Error::new(None::<&str>, Location::UNKNOWN)
+            .chain(std::io::Error::other("first"))
+            .chain(std::io::Error::other("second"));
+        let _ = Error::from(std::io::Error::other("converted")).chain(std::fmt::Error);
+        let _ = Error::builder().build();
+        let _ = Error::builder()
+            .message("built")
+            .source(std::io::Error::other("cause"))
+            .build();
+        assert!(std::panic::catch_unwind(|| error!("hook panic")).is_err());
+        let _ = error!("after panic");
+
+        let current = thread::current().id();
+        let seen = SEEN.lock().unwrap();
+        let seen: Vec<_> = seen.iter().filter(|(id, _)| *id == current).collect();
+        let seen: Vec<_> = seen.iter().map(|(_, err)| err.as_str()).collect();
+        assert_eq!(
+            seen,
+            [
+                "hook test",
+                "hook test",
+                "hook test",
+                "",
+                "",
+                "converted",
+                "",
+                "built: cause",
+                "hook panic",
+                "after panic",
+            ]
+        );
+    }
+}
@@ -0,0 +1,190 @@
+//! Rendering of [`Error`] reports as HTML, see [`Error::html`].
+
+use alloc::string::ToString;
+use core::fmt::{Display, Formatter, Result};
+
+use crate::render::PlainLocation;
+use crate::{Error, Location, Severity};
+
+/// Renders an [`Error`] as semantic HTML, see [`Error::html`].
+#[derive(Debug)]
+pub struct Html<'a>(pub(crate) &'a Error);
+
+/// Writes text with the HTML special characters escaped.
+struct Escaped<'a>(&'a str);
+
+impl Display for Escaped<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let mut rest = self.0;
+        while let Some(index) = rest.find(['&', '<', '>', '"', '\'']) {
+            f.write_str(&rest[..index])?;
+            f.write_str(match rest.as_bytes()[index] {
+                b'&' => "&amp;",
+                b'<' => "&lt;",
+                b'>' => "&gt;",
+                b'"' => "&quot;",
+                _ => "&#39;",
+            })?;
+            rest = &rest[index + 1..];
+        }
+        f.write_str(rest)
+    }
+}
+
+/// Writes the location of a frame after `separator`, if it is known.
+fn write_location(f: &mut Formatter<'_>, separator: &str, location: &Location) -> Result {
+    if !location.is_known() {
+        return Ok(());
+    }
+    let location = PlainLocation(location).to_string();
+    write!(
+        f,
+        "{}<span class=\"fu-location\">{}</span>",
+        separator,
+        Escaped(&location)
+    )
+}
+
+/// Writes `items` as an unordered list with the given class, nothing if there are none.
+fn write_list<'a>(
+    f: &mut Formatter<'_>,
+    class: &str,
+    items: impl Iterator<Item = &'a str>,
+) -> Result {
+    let mut items = items.peekable();
+    if items.peek().is_none() {
+        return Ok(());
+    }
+    writeln!(f, "<ul class=\"{}\">", class)?;
+    for item in items {
+        writeln!(f, "<li>{}</li>", Escaped(item))?;
+    }
+    f.write_str("</ul>\n")
+}
+
+impl Display for Html<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let err = self.0;
+        f.write_str("<div class=\"fu-error\">\n<p class=\"fu-headline\">")?;
+        let mut separator = "";
+        if err.severity() != Severity::Error {
+            write!(
+                f,
+                "<span class=\"fu-severity fu-{0}\">{0}:</span>",
+                err.severity()
+            )?;
+            separator = " ";
+        }
+        if let Some(code) = err.code() {
+            write!(
+                f,
+                "{}<code class=\"fu-code\">{}</code>",
+                separator,
+                Escaped(code)
+            )?;
+            separator = " ";
+        }
+        // An error without a message of its own, like a `?` conversion, is headed by the first
+        // message of its chain.
+        let mut links = crate::render::links(err).into_iter();
+        if let Some(link) = links.next() {
+            write!(
+                f,
+                "{}<span class=\"fu-message\">{}</span>",
+                separator,
+                Escaped(&link.message)
+            )?;
+            separator = " ";
+        }
+        write_location(f, separator, &err.location())?;
+        f.write_str("</p>\n")?;
+
+        let mut links = links.peekable();
+        if links.peek().is_some() {
+            f.write_str("<ol class=\"fu-causes\">\n")?;
+            for link in links {
+                write!(
+                    f,
+                    "<li><span class=\"fu-message\">{}</span>",
+                    Escaped(&link.message)
+                )?;
+                if let Some(location) = &link.location {
+                    write_location(f, " ", location)?;
+                }
+                f.write_str("</li>\n")?;
+            }
+            f.write_str("</ol>\n")?;
+        }
+
+        let mut fields = err.fields().peekable();
+        if fields.peek().is_some() {
+            f.write_str("<dl class=\"fu-fields\">\n")?;
+            for (key, value) in fields {
+                writeln!(f, "<dt>{}</dt><dd>{}</dd>", Escaped(key), Escaped(value))?;
+            }
+            f.write_str("</dl>\n")?;
+        }
+        write_list(f, "fu-notes", err.notes())?;
+        write_list(f, "fu-help", err.helps())?;
+        if let Some(url) = err.url() {
+            writeln!(
+                f,
+                "<p class=\"fu-see\"><a href=\"{0}\">{0}</a></p>",
+                Escaped(url)
+            )?;
+        }
+        f.write_str("</div>")
+    }
+}
+
+#[cfg(all(test, not(fu_no_location)))]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_html() {
+        let err = Error::new(Some("inner <x>"), Location::new("src/a&b.rs", 3, 7))
+            .chain(std::io::Error::other("io \"error\""))
+            .context("outer")
+            .with_code("E1")
+            .with("path", "a<b")
+            .note("it's late")
+            .see("https://example.com/?a=1&b=2");
+        let html = err.html().to_string();
+
+        assert!(html.starts_with("<div class=\"fu-error\">\n<p class=\"fu-headline\"><code class=\"fu-code\">E1</code> <span class=\"fu-message\">outer</span> <span class=\"fu-location\">"));
+        assert!(html.contains("<li><span class=\"fu-message\">inner &lt;x&gt;</span> <span class=\"fu-location\">src/a&amp;b.rs:[3:7]</span></li>\n"));
+        assert!(html.contains("<li><span class=\"fu-message\">io &quot;error&quot;</span></li>\n"));
+        assert!(html.contains("<dl class=\"fu-fields\">\n<dt>path</dt><dd>a&lt;b</dd>\n</dl>\n"));
+        assert!(html.contains("<ul class=\"fu-notes\">\n<li>it&#39;s late</li>\n</ul>\n"));
+        assert!(html.contains("<a href=\"https://example.com/?a=1&amp;b=2\">"));
+        assert!(!html.contains("fu-help"));
+        assert!(html.ends_with("</div>"));
+    }
+
+    #[test]
+    fn test_html_chain() {
+        fn read() -> Result<()> {
+            Err(std::io::Error::other("disk on fire"))?;
+            Ok(())
+        }
+
+        let html = read().unwrap_err().html().to_string();
+        assert!(html.starts_with("<div class=\"fu-error\">\n<p class=\"fu-headline\"><span class=\"fu-message\">disk on fire</span> <span class=\"fu-location\">src/html.rs:["));
+        assert!(!html.contains("fu-causes"));
+
+        let html = Error::new(None::<&str>, Location::new("src/main.rs", 3, 7))
+            .with_code("E1")
+            .html()
+            .to_string();
+        assert!(html.contains("<p class=\"fu-headline\"><code class=\"fu-code\">E1</code> <span class=\"fu-location\">src/main.rs:[3:7]</span></p>\n"));
+
+        let err = error!("outer").chain(error!("inner").context("middle"));
+        let html = err.html().to_string();
+        assert!(html.contains("<li><span class=\"fu-message\">middle</span> <span class=\"fu-location\">src/html.rs:["));
+        assert!(html.contains(
+            "<li><span class=\"fu-message\">inner</span> <span class=\"fu-location\">src/html.rs:["
+        ));
+        assert!(!html.contains("Caused by:"));
+    }
+}
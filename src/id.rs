@@ -0,0 +1,75 @@
+//! [`ErrorId`], a unique identifier stamped on errors with the `id` feature.
+
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A unique identifier of an error occurrence, see [`Error::id`](crate::Error::id).
+///
+/// Like a [ULID](https://github.com/ulid/spec), it is made of a millisecond timestamp followed
+/// by 80 random bits, and renders as 26 characters of Crockford's base32 that sort by creation
+/// time.
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// let err = error!("oops");
+/// let id = err.id().to_string();
+/// assert_eq!(id.len(), 26);
+/// assert_ne!(error!("oops").id(), err.id());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ErrorId(u128);
+
+impl ErrorId {
+    /// Returns the identifier as a number.
+    pub fn as_u128(&self) -> u128 {
+        self.0
+    }
+}
+
+impl core::fmt::Display for ErrorId {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+        for i in (0..26).rev() {
+            let digit = (self.0 >> (i * 5)) & 0x1f;
+            write!(f, "{}", ALPHABET[digit as usize] as char)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns a new identifier.
+pub(crate) fn generate() -> ErrorId {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    // Hashers built by `RandomState` are randomly keyed, mixing in a counter keeps ids created
+    // within the same millisecond distinct.
+    let random = |salt: u64| {
+        let mut hasher = std::hash::RandomState::new().build_hasher();
+        hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+        hasher.write_u64(salt);
+        u128::from(hasher.finish())
+    };
+    let random = ((random(0) << 16) | (random(1) & 0xffff)) & ((1 << 80) - 1);
+    ErrorId(((millis & ((1 << 48) - 1)) << 80) | random)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_id() {
+        assert_eq!(ErrorId(0).to_string(), "00000000000000000000000000");
+        assert_eq!(ErrorId(u128::MAX).to_string(), "7ZZZZZZZZZZZZZZZZZZZZZZZZZ");
+        assert_eq!(ErrorId(32 + 31).to_string(), "0000000000000000000000001Z");
+
+        let (a, b) = (generate(), generate());
+        assert_ne!(a, b);
+        assert!(a.0 >> 80 <= b.0 >> 80);
+    }
+}
@@ -0,0 +1,103 @@
+//! [`ErrorKind`], a coarse category of errors to branch on.
+
+use alloc::borrow::Cow;
+use core::error::Error as StdError;
+
+use crate::Error;
+
+/// A coarse category of errors, to branch on without downcasting to concrete error types.
+///
+/// See [`Error::with_kind`] and [`Error::kind`].
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// let err = error!("no such user").with_kind(ErrorKind::NotFound);
+/// match err.context("failed to get user").kind() {
+///     Some(ErrorKind::NotFound) => {}
+///     _ => unreachable!(),
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// A requested entity does not exist.
+    NotFound,
+    /// The input of an operation is invalid.
+    InvalidInput,
+    /// An operation did not complete in time.
+    Timeout,
+    /// The caller is not allowed to perform an operation.
+    Permission,
+    /// A bug or broken invariant.
+    Internal,
+    /// A category defined by the application.
+    Other(Cow<'static, str>),
+}
+
+impl ErrorKind {
+    /// Creates a category defined by the application.
+    pub fn other<S: Into<Cow<'static, str>>>(name: S) -> Self {
+        ErrorKind::Other(name.into())
+    }
+
+    /// Returns the name of the category, e.g. `not found`.
+    pub fn as_str(&self) -> &str {
+        match self {
+            ErrorKind::NotFound => "not found",
+            ErrorKind::InvalidInput => "invalid input",
+            ErrorKind::Timeout => "timeout",
+            ErrorKind::Permission => "permission denied",
+            ErrorKind::Internal => "internal",
+            ErrorKind::Other(name) => name,
+        }
+    }
+}
+
+impl core::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Returns the kind of a cause, see [`Error::kind`].
+pub(crate) fn cause_kind(cause: &(dyn StdError + 'static)) -> Option<ErrorKind> {
+    if let Some(err) = cause.downcast_ref::<Error>() {
+        return err.kind();
+    }
+    #[cfg(feature = "std")]
+    if let Some(err) = cause.downcast_ref::<std::io::Error>() {
+        use std::io::ErrorKind::*;
+        return match err.kind() {
+            NotFound => Some(ErrorKind::NotFound),
+            InvalidInput | InvalidData => Some(ErrorKind::InvalidInput),
+            TimedOut => Some(ErrorKind::Timeout),
+            PermissionDenied => Some(ErrorKind::Permission),
+            _ => None,
+        };
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_kind() {
+        assert_eq!(error!("oops").kind(), None);
+        let err = error!("oops")
+            .with_kind(ErrorKind::Timeout)
+            .context("outer");
+        assert_eq!(err.kind(), Some(ErrorKind::Timeout));
+        let err = err.with_kind(ErrorKind::other("quota"));
+        assert_eq!(err.kind().unwrap().to_string(), "quota");
+
+        let missing = std::io::Error::from(std::io::ErrorKind::NotFound);
+        let err = Error::from(missing).context("failed to open config");
+        assert_eq!(err.kind(), Some(ErrorKind::NotFound));
+        let denied = error!("denied").with_kind(ErrorKind::Permission);
+        let err = error!("outer").chain(std::fmt::Error).chain(denied);
+        assert_eq!(err.kind(), Some(ErrorKind::Permission));
+    }
+}
@@ -3,7 +3,9 @@
 //! Just an [`Error`] with its location and helpful macros.
 //!
 //! - Custom `Error` type with file name, line, and column information.
-//! - Short and convenient macros: `error!`, `bail!`, and `ensure!`.
+//! - Short and convenient macros: `error!`, `bail!`, `bail_if!`, `ensure!` and `ensure_some!`.
+//! - `Context` and `Wrap` traits to attach context to any `Result` or `Option`, recording the
+//!   call site.
 //! - Lightweight.
 //!
 //! ## Usage
@@ -27,20 +29,220 @@
 //!     example(-1)
 //! }
 //!
-//! // Error: value must be non-negative    examples/foo.rs:[4:5]
+//! // Error: value must be non-negative    examples/foo.rs:[6:5] in foo::example
 //!```
+//!
+//! ## Features
+//!
+//! - `std` (default): integrate with the standard library, e.g. conversions from
+//!   `std::io::Error`, environment-based configuration and [`Report`]. Without it the crate is
+//!   `no_std` and only requires `alloc`; errors are then rendered without colors.
+//! - `actix`: implement `ResponseError` for `Error`, like the `axum` feature.
+//! - `anyhow`: convert an `anyhow::Error` into an `Error`, keeping its chain. An `Error`
+//!   converted into an `anyhow::Error` converts back unchanged.
+//! - `axum`: implement `IntoResponse` for `Error`, responding with its status and a body in the
+//!   format set with `set_body_format`, JSON with its code and message by default.
+//! - `backtrace`: capture a [`std::backtrace::Backtrace`] when an `Error` is created. Capturing
+//!   is controlled by the `RUST_BACKTRACE` and `RUST_LIB_BACKTRACE` environment variables.
+//! - `derive`: `#[derive(FuError)]` for typed error enums, generating `Display`, `Error` and a
+//!   conversion into `Error` that records the location of the conversion.
+//! - `eyre`: convert an `eyre::Report` into an `Error`, keeping its chain. An `Error`
+//!   converted into an `eyre::Report` converts back unchanged.
+//! - `ffi`: the `ffi` module, to inspect errors from C through an opaque handle.
+//! - `html`: render an `Error` as semantic HTML with `Error::html`, for dashboards and emails.
+//! - `id`: stamp every `Error` with a unique, ULID-like identifier, rendered after the chain.
+//! - `json`: render errors as JSON objects when the `FU_STYLE` environment variable is `json`.
+//! - `log`: log the full chain of an error at a chosen level with `Error::log`, or of a
+//!   `Result` with `ResultExt::log_err`, passing it through unchanged or falling back to a default
+//!   with `ResultExt::or_default_logged`.
+//! - `markdown`: render an `Error` as Markdown with `Error::markdown`, for issues and chats.
+//! - `metrics`: count created and reported errors with `metrics` counters, labeled by code, kind
+//!   and file.
+//! - `miette`: implement `miette::Diagnostic` for `Error`, with its code, help messages and URL.
+//! - `nightly`: implement `Try` for `Report`, so that `?` and `do yeet` work in a `main`
+//!   returning it, and `Error::provide`, so that the location and backtrace of an `Error` can be
+//!   requested with `core::error::request_ref`. Requires a nightly compiler. `do yeet` already
+//!   works in functions returning a `Result`.
+//! - `pyo3`: convert an `Error` into a Python `ValueError` with its location and chain.
+//! - `sentry`: send an `Error` to Sentry as an event with its chain, locations and fields, and
+//!   optionally every reported error with `set_capture_reported`.
+//! - `serde`: implement `Serialize` for `Error`, including its location and the full chain, and
+//!   add `DeserializedError` to reconstruct it on the other side.
+//! - `snippets`: show the source line of every location, with a caret under its column, in
+//!   the alternate format. The file is read when the error is rendered.
+//! - `terminal`: query the size of the terminal to wrap long messages at its width, when the
+//!   `COLUMNS` environment variable is not set.
+//! - `thread`: record the thread an `Error` is created on, rendered in the alternate format.
+//! - `timestamp`: record the time an `Error` is created at, rendered in the alternate format,
+//!   from a clock that can be replaced with `set_clock`.
+//! - `tonic`: convert an `Error` into a `tonic::Status`, with a gRPC code taken from its code or
+//!   status and the messages of its chain.
+//! - `tracing`: capture a `tracing_error::SpanTrace` when an `Error` is created, rendered in
+//!   the alternate format, and optionally emit an event for every error with `set_trace_events`.
+//! - `wasm`: convert an `Error` into a JavaScript `Error` for `wasm_bindgen` functions.
+//!
+//! Building with `RUSTFLAGS="--cfg fu_no_location"` compiles locations out, so that no source
+//! paths are embedded in the binary: locations are zero-sized and omitted from every rendering.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+#![cfg_attr(
+    feature = "nightly",
+    feature(error_generic_member_access, try_trait_v2, try_trait_v2_yeet)
+)]
 
-use std::error::Error as StdError;
+extern crate alloc;
 
-type Location = (&'static str, u32, u32);
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::error::Error as StdError;
+
+#[cfg(feature = "actix")]
+mod actix;
+#[cfg(feature = "anyhow")]
+mod anyhow;
+#[cfg(feature = "axum")]
+mod axum;
+mod builder;
+#[cfg(feature = "eyre")]
+mod eyre;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod group;
+#[cfg(feature = "std")]
+mod hook;
+#[cfg(feature = "html")]
+mod html;
+#[cfg(feature = "id")]
+mod id;
+mod kind;
+mod location;
+#[cfg(feature = "log")]
+mod logging;
+#[cfg(feature = "markdown")]
+mod markdown;
+#[cfg(feature = "metrics")]
+mod metrics;
+#[cfg(feature = "miette")]
+mod miette;
+#[cfg(feature = "std")]
+mod panic;
+#[cfg(feature = "pyo3")]
+mod pyo3;
+mod render;
+#[cfg(feature = "std")]
+mod report;
+#[cfg(feature = "sentry")]
+mod sentry;
+#[cfg(feature = "serde")]
+mod serde;
+mod severity;
+#[cfg(feature = "timestamp")]
+mod timestamp;
+#[cfg(feature = "tonic")]
+mod tonic;
+#[cfg(feature = "tracing")]
+mod trace;
+mod translate;
+#[cfg(feature = "wasm")]
+mod wasm;
+#[cfg(any(feature = "actix", feature = "axum"))]
+mod web;
+
+pub use builder::ErrorBuilder;
+#[cfg(feature = "derive")]
+pub use fu_derive::FuError;
+pub use group::{CollectAll, ErrorGroup};
+#[cfg(feature = "std")]
+pub use hook::set_error_hook;
+#[cfg(feature = "html")]
+pub use html::Html;
+#[cfg(feature = "id")]
+pub use id::ErrorId;
+pub use kind::ErrorKind;
+pub use location::Location;
+#[cfg(feature = "log")]
+pub use logging::ResultExt;
+#[cfg(feature = "markdown")]
+pub use markdown::Markdown;
+#[cfg(feature = "std")]
+pub use panic::{catch, install_panic_hook, Panic};
+pub use render::{
+    color_enabled, columns_enabled, path_style, render_default, set_color, set_columns,
+    set_path_style, set_wrap_width, theme, wrap_width, Compact, PathStyle, RenderOptions, Theme,
+    Themed,
+};
+#[cfg(feature = "std")]
+pub use render::{
+    detect_color, detect_width, remap_path, set_hyperlinks, set_location_format, set_report_hook,
+    set_theme, FILE_LINK, VSCODE_LINK,
+};
+#[cfg(feature = "std")]
+pub use report::{exit, Report};
+#[cfg(feature = "sentry")]
+pub use sentry::set_capture_reported;
+#[cfg(feature = "serde")]
+pub use serde::DeserializedError;
+pub use severity::Severity;
+#[cfg(feature = "timestamp")]
+pub use timestamp::set_clock;
+#[cfg(feature = "tracing")]
+pub use trace::set_trace_events;
+#[cfg(feature = "std")]
+pub use translate::set_translator;
+pub use translate::MessageKey;
+#[cfg(any(feature = "actix", feature = "axum"))]
+pub use web::{body_format, set_body_format, BodyFormat};
 
 /// A custom error type that contains file location and a message.
 ///
 /// This struct is used to represent errors with additional context like the file name,
 /// line, and column where the error occurred, along with a user-defined message.
+///
+/// Every call to [`Error::context`] pushes a new frame onto the error, so layered context is
+/// never lost.
+///
+/// Errors are cheap to clone: the source is shared between clones.
+#[derive(Clone)]
 pub struct Error {
-    message: Option<String>,
-    location: Location,
+    inner: Box<ErrorImpl>,
+}
+
+/// The contents of an [`Error`], boxed to keep `Result<T, Error>` small.
+#[derive(Clone)]
+struct ErrorImpl {
+    /// Context frames, innermost first.
+    frames: Vec<Frame>,
+    source: Option<Arc<dyn StdError + Send + Sync>>,
+    fields: Vec<(String, String)>,
+    /// The values of the fields attached with `with_secret`, which are redacted in `fields`.
+    secrets: Vec<(String, String)>,
+    exit_code: Option<u8>,
+    status: Option<u16>,
+    transient: Option<bool>,
+    kind: Option<ErrorKind>,
+    severity: Option<Severity>,
+    code: Option<String>,
+    help: Vec<String>,
+    notes: Vec<String>,
+    url: Option<String>,
+    /// Typed values attached with `with_payload`, innermost first.
+    payloads: Vec<Arc<dyn Any + Send + Sync>>,
+    /// Whether the error was reported as created, which happens only once, see `created`.
+    created: bool,
+    #[cfg(feature = "backtrace")]
+    backtrace: Arc<std::backtrace::Backtrace>,
+    #[cfg(feature = "tracing")]
+    span_trace: tracing_error::SpanTrace,
+    #[cfg(feature = "timestamp")]
+    timestamp: std::time::SystemTime,
+    #[cfg(feature = "thread")]
+    thread: std::thread::Thread,
+    #[cfg(feature = "id")]
+    id: ErrorId,
 }
 
 impl Error {
@@ -48,124 +250,2415 @@ impl Error {
     ///
     /// # Example
     /// ```
-    /// # use fu::Error;
-    /// let err = Error::new(Some("oops"), ("main.rs", 10, 15));
+    /// # use fu::{Error, Location};
+    /// let err = Error::new(Some("oops"), Location::new("main.rs", 10, 15));
     /// println!("{}", err); // oops    main.rs:[10:15]
     /// ```
-    pub fn new<S: Into<String>>(message: Option<S>, location: Location) -> Self {
+    pub fn new<S, L>(message: Option<S>, location: L) -> Self
+    where
+        S: Into<Cow<'static, str>>,
+        L: Into<Location>,
+    {
+        let mut err = Error::new_unreported(message.map(Into::into), location.into());
+        err.created();
+        err
+    }
+
+    /// Creates an error that is not reported as created yet, for conversions: they are reported
+    /// once their source is attached.
+    pub(crate) fn new_unreported(message: Option<Cow<'static, str>>, location: Location) -> Self {
         Error {
-            message: message.map(|c| c.into()),
-            location,
+            inner: Box::new(ErrorImpl {
+                frames: alloc::vec![Frame::new(message, location)],
+                source: None,
+                fields: Vec::new(),
+                secrets: Vec::new(),
+                exit_code: None,
+                status: None,
+                transient: None,
+                kind: None,
+                severity: None,
+                code: None,
+                help: Vec::new(),
+                notes: Vec::new(),
+                payloads: Vec::new(),
+                url: None,
+                created: false,
+                #[cfg(feature = "backtrace")]
+                backtrace: Arc::new(std::backtrace::Backtrace::capture()),
+                #[cfg(feature = "tracing")]
+                span_trace: tracing_error::SpanTrace::capture(),
+                #[cfg(feature = "timestamp")]
+                timestamp: timestamp::now(),
+                #[cfg(feature = "thread")]
+                thread: std::thread::current(),
+                #[cfg(feature = "id")]
+                id: id::generate(),
+            }),
         }
     }
-}
 
-impl std::fmt::Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if let Some(msg) = &self.message {
-            write!(f, "{}    ", msg)?;
+    /// Reports a newly created error to the [error hook](set_error_hook) and to `tracing`, unless
+    /// it already was.
+    fn created(&mut self) {
+        if !core::mem::replace(&mut self.inner.created, true) {
+            #[cfg(feature = "std")]
+            hook::call(self);
+            #[cfg(feature = "tracing")]
+            trace::emit_event(self);
+            #[cfg(feature = "metrics")]
+            metrics::record_created(self);
         }
-        write!(
-            f,
-            "\x1b[90m{}:[{}:{}]\x1b[0m",
-            self.location.0, self.location.1, self.location.2
-        )
     }
-}
 
-impl std::fmt::Debug for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self)
+    /// Reports an error printed or logged by the crate to `metrics` and Sentry.
+    #[cfg(feature = "std")]
+    pub(crate) fn reported(&self) {
+        #[cfg(feature = "metrics")]
+        metrics::record_reported(self);
+        #[cfg(feature = "sentry")]
+        sentry::capture_reported(self);
     }
-}
 
-impl From<std::io::Error> for Error {
-    fn from(err: std::io::Error) -> Self {
-        Error::new(Some(err.to_string()), (file!(), line!(), column!()))
+    /// Creates an error with the given message, recording the location of the caller.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// let result: Result<u16> = "70000".parse::<u16>().map_err(|_| Error::msg("port out of range"));
+    /// assert_eq!(result.unwrap_err().message(), Some("port out of range"));
+    /// ```
+    #[cfg_attr(not(fu_no_location), track_caller)]
+    pub fn msg<M: core::fmt::Display>(message: M) -> Self {
+        Error::new(Some(message.to_string()), Location::caller())
     }
-}
 
-impl From<std::num::ParseIntError> for Error {
-    fn from(err: std::num::ParseIntError) -> Self {
-        Error::new(Some(err.to_string()), (file!(), line!(), column!()))
+    /// Creates an error whose message is only formatted by `format` when it is first used, e.g.
+    /// to render the error, recording the location of the caller.
+    ///
+    /// This avoids the cost of formatting for errors that are usually discarded. The
+    /// `error!(lazy ...)` syntax captures its arguments in such a closure.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    ///
+    /// static FORMATTED: AtomicBool = AtomicBool::new(false);
+    /// let id = 42;
+    /// let err = Error::lazy(move || {
+    ///     FORMATTED.store(true, Ordering::Relaxed);
+    ///     format!("no such user: {}", id)
+    /// });
+    /// assert!(!FORMATTED.load(Ordering::Relaxed));
+    /// assert_eq!(err.message(), Some("no such user: 42"));
+    ///
+    /// let err = error!(lazy "no such user: {}", id);
+    /// assert_eq!(err.message(), Some("no such user: 42"));
+    /// ```
+    #[cfg(feature = "std")]
+    #[cfg_attr(not(fu_no_location), track_caller)]
+    pub fn lazy<F>(format: F) -> Self
+    where
+        F: Fn() -> String + Send + Sync + 'static,
+    {
+        Error::new_lazy(Box::new(format), Location::caller())
     }
-}
 
-impl StdError for Error {}
+    #[cfg(feature = "std")]
+    fn new_lazy(format: Box<dyn Fn() -> String + Send + Sync>, location: Location) -> Self {
+        let mut err = Error::new_unreported(None, location);
+        err.head_mut().lazy = Some(Arc::new(LazyMessage {
+            format,
+            message: std::sync::OnceLock::new(),
+        }));
+        err.created();
+        err
+    }
 
-/// [`Result`]<T, [`Error`]>.
-pub type Result<T> = std::result::Result<T, Error>;
+    /// Returns a builder to set the message and attributes of a new error in one chain.
+    ///
+    /// The error is created at the caller, unless a location is given.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// let err = Error::builder()
+    ///     .message("user not found")
+    ///     .code("E404")
+    ///     .kind(ErrorKind::NotFound)
+    ///     .help("check the user id")
+    ///     .field("user_id", 42)
+    ///     .source(std::io::Error::other("no rows"))
+    ///     .build();
+    /// assert_eq!(err.code(), Some("E404"));
+    /// assert!(err.to_string().contains("Caused by: no rows"));
+    /// ```
+    #[cfg_attr(not(fu_no_location), track_caller)]
+    pub fn builder() -> ErrorBuilder {
+        ErrorBuilder::new()
+    }
 
-/// Construct a Result with the crates [`Error`] type.
-///
-/// This macro uses the `file!()`, `line!()`, and `column!()` macros to automatically
-/// capture the file and location of the error.
-///
-/// # Example
-/// ```
-/// # use fu::*;
-/// let result: Result<()> = Err(error!("oops"));
-/// assert!(result.is_err());
-/// ```
-#[macro_export]
-macro_rules! error {
-    () => {
-        $crate::Error::new(
-            None,
-            (file!(), line!(), column!()),
-        )
-    };
-    ($($arg:tt)*) => {
-        $crate::Error::new(
-            Some(format!($($arg)*)),
-            (file!(), line!(), column!()),
-        )
-    };
+    /// Returns the message of the outermost frame, the one rendered first.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// let err = error!("inner").context("outer");
+    /// assert_eq!(err.message(), Some("outer"));
+    /// assert_eq!(error!().message(), None);
+    /// ```
+    pub fn message(&self) -> Option<&str> {
+        self.head().message()
+    }
+
+    /// Returns the location of the outermost frame, where the last context was attached.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// let err = error!("oops");
+    /// # #[cfg(not(fu_no_location))]
+    /// assert_eq!(err.location().file(), file!());
+    /// # #[cfg(not(fu_no_location))]
+    /// assert_eq!(err.location().line(), line!() - 4);
+    /// ```
+    pub fn location(&self) -> Location {
+        self.head().location
+    }
+
+    /// Returns true if this error wraps an underlying source error.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// assert!(!error!("oops").has_source());
+    /// assert!(error!(std::fmt::Error).has_source());
+    /// ```
+    pub fn has_source(&self) -> bool {
+        self.inner.source.is_some()
+    }
+
+    fn head(&self) -> &Frame {
+        self.inner
+            .frames
+            .last()
+            .expect("an error has at least one frame")
+    }
+
+    fn head_mut(&mut self) -> &mut Frame {
+        self.inner
+            .frames
+            .last_mut()
+            .expect("an error has at least one frame")
+    }
+
+    /// Returns the backtrace captured when this error was created.
+    ///
+    /// The backtrace is only captured if the `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE`
+    /// environment variables are set, see [`std::backtrace::Backtrace::capture`].
+    #[cfg(feature = "backtrace")]
+    pub fn backtrace(&self) -> &std::backtrace::Backtrace {
+        &self.inner.backtrace
+    }
+
+    /// Returns the span trace captured when this error was created.
+    ///
+    /// The span trace is only captured if a subscriber with a [`tracing_error::ErrorLayer`] is
+    /// installed. It is rendered in the alternate format (`{:#}`).
+    #[cfg(feature = "tracing")]
+    pub fn span_trace(&self) -> &tracing_error::SpanTrace {
+        &self.inner.span_trace
+    }
+
+    /// Returns the time the innermost error of the chain was created at, see [`set_clock`].
+    ///
+    /// It is rendered in UTC in the alternate format (`{:#}`).
+    #[cfg(feature = "timestamp")]
+    pub fn timestamp(&self) -> std::time::SystemTime {
+        self.inner.timestamp
+    }
+
+    /// Returns the thread the innermost error of the chain was created on.
+    ///
+    /// Its name and ID are rendered in the alternate format (`{:#}`).
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// let err = std::thread::Builder::new()
+    ///     .name("worker-1".into())
+    ///     .spawn(|| error!("oops"))
+    ///     .unwrap()
+    ///     .join()
+    ///     .unwrap();
+    /// assert_eq!(err.context("job failed").thread().name(), Some("worker-1"));
+    /// ```
+    #[cfg(feature = "thread")]
+    pub fn thread(&self) -> &std::thread::Thread {
+        &self.inner.thread
+    }
+
+    /// Returns the unique identifier of this error occurrence, rendered after the chain.
+    ///
+    /// Wrapping an error keeps the identifier of the innermost one.
+    #[cfg(feature = "id")]
+    pub fn id(&self) -> ErrorId {
+        self.inner.id
+    }
+
+    /// Logs the full chain of this error, in the alternate format (`{:#}`), at the given
+    /// `level` and returns the error unchanged.
+    ///
+    /// The record points at the location of the error rather than at the caller.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// fn load() -> Result<()> {
+    ///     Err(error!("config not found").log(log::Level::Warn))
+    /// }
+    /// ```
+    #[cfg(feature = "log")]
+    pub fn log(self, level: log::Level) -> Self {
+        logging::log(&self, level);
+        self
+    }
+
+    /// Sends this error to the current Sentry hub and returns the ID of the event.
+    ///
+    /// See [`sentry_event`](Error::sentry_event) for the content of the event.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// if let Err(err) = std::fs::read("config.toml").wrap("failed to read config") {
+    ///     err.capture();
+    /// }
+    /// ```
+    #[cfg(feature = "sentry")]
+    pub fn capture(&self) -> sentry_core::types::Uuid {
+        sentry::capture(self)
+    }
+
+    /// Returns the Sentry event of this error.
+    ///
+    /// Its exceptions are the causes followed by the frames, innermost first, each frame with its
+    /// location as a stack frame. The backtrace, if captured, is attached to the outermost one.
+    /// Fields are set as extra data, the code and kind as tags and the severity as the level.
+    #[cfg(feature = "sentry")]
+    pub fn sentry_event(&self) -> sentry_core::protocol::Event<'static> {
+        sentry::event(self)
+    }
+
+    /// Adds a new layer of context to this error, recording the location of the caller.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// let err = error!("connection refused").context("something went wrong");
+    /// assert!(err.to_string().starts_with("something went wrong"));
+    /// assert!(err.to_string().contains("connection refused"));
+    /// ```
+    #[cfg_attr(not(fu_no_location), track_caller)]
+    pub fn context<C: Into<Cow<'static, str>>>(mut self, context: C) -> Self {
+        self.inner
+            .frames
+            .push(Frame::new(Some(context.into()), Location::caller()));
+        self
+    }
+
+    /// Adds a new layer of context to this error, computing it with the given closure.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// let path = "config.toml";
+    /// let err = error!("not found").with_context(|| format!("failed to read {}", path));
+    /// assert!(err.to_string().contains("config.toml"));
+    /// ```
+    #[cfg_attr(not(fu_no_location), track_caller)]
+    pub fn with_context<C, F>(self, f: F) -> Self
+    where
+        C: Into<Cow<'static, str>>,
+        F: FnOnce() -> C,
+    {
+        self.context(f())
+    }
+
+    /// Sets the underlying cause of this error.
+    ///
+    /// If the source is itself an [`Error`], its frames are kept below the frames of this one
+    /// instead of being boxed as an opaque source. If this error already has a source, the new
+    /// one is nested under the existing causes instead of replacing them.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// let io = std::io::Error::other("disk on fire");
+    /// let err = error!("failed to save").chain(io);
+    /// assert!(err.to_string().contains("disk on fire"));
+    ///
+    /// let err = err.chain(std::fmt::Error);
+    /// assert!(err.to_string().contains("Caused by: disk on fire"));
+    /// assert!(err.to_string().contains("Caused by: an error occurred when formatting"));
+    /// ```
+    pub fn chain<E>(self, source: E) -> Self
+    where
+        E: StdError + Send + Sync + 'static,
+    {
+        let mut source = Some(source);
+        if let Some(inner) =
+            (&mut source as &mut dyn core::any::Any).downcast_mut::<Option<Error>>()
+        {
+            let inner = inner.take().unwrap();
+            if self.inner.source.is_none() {
+                return self.absorb(inner);
+            }
+            // The frames of `inner` cannot go below an opaque source, keep it whole instead.
+            return self.set_source(Arc::new(inner));
+        }
+        self.set_source(Arc::new(source.unwrap()))
+    }
+
+    /// Creates an error from a boxed error, recording the location of the caller.
+    ///
+    /// If the boxed error is itself an [`Error`], it is given back unchanged.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// fn connect() -> core::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ///     Err("connection refused".into())
+    /// }
+    ///
+    /// let err = connect().map_err(Error::from_boxed).unwrap_err();
+    /// assert!(err.to_string().starts_with("connection refused"));
+    /// ```
+    ///
+    /// The other way around, an `Error` converts into a `Box<dyn Error + Send + Sync>` with `?`
+    /// through the conversion of the standard library for every error type. The boxed error
+    /// still renders the locations of every frame, with `Display` and `Debug`.
+    ///
+    /// ```
+    /// # use fu::*;
+    /// fn load() -> core::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ///     Err(error!("config not found"))?
+    /// }
+    ///
+    /// let boxed = load().unwrap_err();
+    /// # #[cfg(not(fu_no_location))]
+    /// assert!(boxed.to_string().contains(file!()));
+    /// assert_eq!(Error::from_boxed(boxed).message(), Some("config not found"));
+    /// ```
+    #[cfg_attr(not(fu_no_location), track_caller)]
+    pub fn from_boxed(err: Box<dyn StdError + Send + Sync>) -> Self {
+        match err.downcast::<Error>() {
+            Ok(err) => *err,
+            Err(err) => Error::new_unreported(None, Location::caller()).set_source(Arc::from(err)),
+        }
+    }
+
+    fn set_source(mut self, source: Arc<dyn StdError + Send + Sync>) -> Self {
+        self.inner.source = Some(match self.inner.source.take() {
+            Some(existing) => Nested::push(existing, source),
+            None => source,
+        });
+        // Conversions are reported once their source is attached.
+        self.created();
+        self
+    }
+
+    /// Puts everything from `inner` underneath the frames of this error.
+    fn absorb(mut self, other: Error) -> Self {
+        let (this, mut other) = (&mut *self.inner, other.inner);
+        other.frames.append(&mut this.frames);
+        this.frames = other.frames;
+        this.source = other.source;
+        other.fields.append(&mut this.fields);
+        this.fields = other.fields;
+        other.secrets.append(&mut this.secrets);
+        this.secrets = other.secrets;
+        this.exit_code = this.exit_code.or(other.exit_code);
+        this.status = this.status.or(other.status);
+        this.transient = this.transient.or(other.transient);
+        this.kind = this.kind.take().or(other.kind);
+        this.severity = this.severity.or(other.severity);
+        this.code = this.code.take().or(other.code);
+        this.url = this.url.take().or(other.url);
+        other.help.append(&mut this.help);
+        this.help = other.help;
+        other.notes.append(&mut this.notes);
+        this.notes = other.notes;
+        other.payloads.append(&mut this.payloads);
+        this.payloads = other.payloads;
+        // The error was reported when `other` was created.
+        this.created |= other.created;
+        #[cfg(feature = "backtrace")]
+        {
+            this.backtrace = other.backtrace;
+        }
+        #[cfg(feature = "tracing")]
+        {
+            this.span_trace = other.span_trace;
+        }
+        #[cfg(feature = "timestamp")]
+        {
+            this.timestamp = other.timestamp;
+        }
+        #[cfg(feature = "thread")]
+        {
+            this.thread = other.thread;
+        }
+        #[cfg(feature = "id")]
+        {
+            this.id = other.id;
+        }
+        self
+    }
+
+    /// Attaches a stable key and arguments to the outermost message of this error, to translate
+    /// it when it is rendered, see [`set_translator`].
+    ///
+    /// The message itself is unchanged and returned by [`message`](Error::message).
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// let key = MessageKey::new("config.not_found").with("path", "app.toml");
+    /// let err = error!("config not found: app.toml").with_message_key(key);
+    /// assert_eq!(err.frames().next().unwrap().message_key().unwrap().key(), "config.not_found");
+    /// ```
+    pub fn with_message_key(mut self, key: MessageKey) -> Self {
+        self.head_mut().key = Some(Box::new(key));
+        self
+    }
+
+    /// Attaches a key-value field to this error.
+    ///
+    /// Fields carry dynamic data, such as identifiers or paths, separately from the message and
+    /// are rendered after the chain.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// let err = error!("request failed").with("request_id", 42).with("retries", 3);
+    /// let fields: Vec<_> = err.fields().collect();
+    /// assert_eq!(fields, [("request_id", "42"), ("retries", "3")]);
+    /// ```
+    pub fn with<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: core::fmt::Display,
+    {
+        self.inner.fields.push((key.into(), value.to_string()));
+        self
+    }
+
+    /// Returns an iterator over the key-value fields attached to this error, in insertion order.
+    pub fn fields(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.inner
+            .fields
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Attaches a key-value field holding a sensitive value, such as a password or a token.
+    ///
+    /// The field is rendered, serialized and returned by [`fields`](Error::fields) with a
+    /// `[REDACTED]` value. The value itself is only available through
+    /// [`secret`](Error::secret) and [`secrets`](Error::secrets), for privileged renderers.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// let err = error!("login failed").with("user", "admin").with_secret("password", "hunter2");
+    /// assert!(!err.to_string().contains("hunter2"));
+    /// assert!(err.to_string().contains("password = [REDACTED]"));
+    /// assert_eq!(err.secret("password"), Some("hunter2"));
+    /// ```
+    pub fn with_secret<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: core::fmt::Display,
+    {
+        let key = key.into();
+        self.inner.fields.push((key.clone(), REDACTED.into()));
+        self.inner.secrets.push((key, value.to_string()));
+        self
+    }
+
+    /// Returns the value of the last secret field attached with the given `key`, if any.
+    pub fn secret(&self, key: &str) -> Option<&str> {
+        self.secrets()
+            .filter(|(k, _)| *k == key)
+            .last()
+            .map(|(_, v)| v)
+    }
+
+    /// Returns an iterator over the secret fields attached to this error, with their values, in
+    /// insertion order.
+    pub fn secrets(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.inner
+            .secrets
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Sets the exit code the process should terminate with when this error reaches the top.
+    ///
+    /// It is used by [`exit`] and [`Report`].
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// let err = error!("invalid arguments").with_exit_code(2);
+    /// assert_eq!(err.exit_code(), Some(2));
+    /// ```
+    pub fn with_exit_code(mut self, code: u8) -> Self {
+        self.inner.exit_code = Some(code);
+        self
+    }
+
+    /// Returns the exit code attached to this error, if any.
+    pub fn exit_code(&self) -> Option<u8> {
+        self.inner.exit_code
+    }
+
+    /// Sets the HTTP status code a web service should respond with for this error.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// let err = error!("user not found").with_status(404);
+    /// assert_eq!(err.status(), Some(404));
+    /// ```
+    pub fn with_status(mut self, status: u16) -> Self {
+        self.inner.status = Some(status);
+        self
+    }
+
+    /// Returns the HTTP status code attached to this error, if any.
+    pub fn status(&self) -> Option<u16> {
+        self.inner.status
+    }
+
+    /// Marks this error as transient: retrying the operation that failed may succeed.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// let err = error!("database is busy").transient();
+    /// assert!(err.context("failed to save user").is_transient());
+    /// ```
+    pub fn transient(mut self) -> Self {
+        self.inner.transient = Some(true);
+        self
+    }
+
+    /// Marks this error as permanent: retrying the operation that failed will not succeed.
+    pub fn permanent(mut self) -> Self {
+        self.inner.transient = Some(false);
+        self
+    }
+
+    /// Returns true if retrying the operation that failed may succeed.
+    ///
+    /// The outermost [`transient`](Error::transient) or [`permanent`](Error::permanent) marker
+    /// decides. Without one, the error is transient if one of its causes is: a marked `Error`,
+    /// or an I/O error that timed out, was interrupted, would block or lost its connection.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// let timeout = std::io::Error::from(std::io::ErrorKind::TimedOut);
+    /// let err = Error::from(timeout).context("failed to fetch");
+    /// assert!(err.is_transient());
+    /// assert!(!err.permanent().is_transient());
+    /// ```
+    pub fn is_transient(&self) -> bool {
+        match self.inner.transient {
+            Some(transient) => transient,
+            None => self.causes().any(is_transient_cause),
+        }
+    }
+
+    /// Sets the category of this error, see [`ErrorKind`].
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// let err = error!("request timed out").with_kind(ErrorKind::Timeout);
+    /// assert_eq!(err.kind(), Some(ErrorKind::Timeout));
+    /// ```
+    pub fn with_kind(mut self, kind: ErrorKind) -> Self {
+        self.inner.kind = Some(kind);
+        self
+    }
+
+    /// Returns the category of this error, if known.
+    ///
+    /// The outermost kind set with [`with_kind`](Error::with_kind) wins. Without one, the kind is
+    /// that of the first cause that has one: an `Error` with a kind, or an I/O error of a
+    /// matching [`std::io::ErrorKind`].
+    pub fn kind(&self) -> Option<ErrorKind> {
+        match &self.inner.kind {
+            Some(kind) => Some(kind.clone()),
+            None => self.causes().find_map(kind::cause_kind),
+        }
+    }
+
+    /// Sets how serious this error is, see [`Severity`].
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// let err = error!("disk is full").with_severity(Severity::Fatal);
+    /// assert_eq!(err.context("failed to save").severity(), Severity::Fatal);
+    /// ```
+    pub fn with_severity(mut self, severity: Severity) -> Self {
+        self.inner.severity = Some(severity);
+        self
+    }
+
+    /// Returns how serious this error is, [`Severity::Error`] unless set otherwise.
+    pub fn severity(&self) -> Severity {
+        self.inner.severity.unwrap_or_default()
+    }
+
+    /// Sets a machine-readable code identifying this kind of error.
+    ///
+    /// The code is rendered in front of the message and can also be set with the
+    /// `error!(code = "...", ...)` syntax.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// let err = error!("invalid token").with_code("E1023");
+    /// assert_eq!(err.code(), Some("E1023"));
+    /// assert!(err.to_string().starts_with("[E1023] invalid token"));
+    /// ```
+    pub fn with_code<C: Into<String>>(mut self, code: C) -> Self {
+        self.inner.code = Some(code.into());
+        self
+    }
+
+    /// Returns the code attached to this error, if any.
+    pub fn code(&self) -> Option<&str> {
+        self.inner.code.as_deref()
+    }
+
+    /// Adds a help message suggesting how to fix this error.
+    ///
+    /// Help messages are rendered in a separate `help:` section after the chain.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// let err = error!("destination exists").help("try running with --force");
+    /// assert!(err.to_string().contains("help: try running with --force"));
+    /// ```
+    pub fn help<H: Into<String>>(mut self, help: H) -> Self {
+        self.inner.help.push(help.into());
+        self
+    }
+
+    /// Returns an iterator over the help messages attached to this error.
+    pub fn helps(&self) -> impl Iterator<Item = &str> {
+        self.inner.help.iter().map(String::as_str)
+    }
+
+    /// Adds an informational note, such as what the program was doing when this error occurred.
+    ///
+    /// Notes are rendered as `note:` lines after the chain, before the help messages.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// let err = error!("invalid port").note("config was loaded from ~/.config/app.toml");
+    /// assert!(err.to_string().contains("note: config was loaded from ~/.config/app.toml"));
+    /// ```
+    pub fn note<N: Into<String>>(mut self, note: N) -> Self {
+        self.inner.notes.push(note.into());
+        self
+    }
+
+    /// Returns an iterator over the notes attached to this error.
+    pub fn notes(&self) -> impl Iterator<Item = &str> {
+        self.inner.notes.iter().map(String::as_str)
+    }
+
+    /// Links to a documentation or troubleshooting page about this error.
+    ///
+    /// The URL is rendered as a `see:` line after the help messages. The outermost one wins.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// let err = error!("disk quota exceeded").see("https://example.com/runbooks/quota");
+    /// assert_eq!(err.url(), Some("https://example.com/runbooks/quota"));
+    /// assert!(err.to_string().contains("see: https://example.com/runbooks/quota"));
+    /// ```
+    pub fn see<U: Into<String>>(mut self, url: U) -> Self {
+        self.inner.url = Some(url.into());
+        self
+    }
+
+    /// Returns the documentation URL attached to this error, if any.
+    pub fn url(&self) -> Option<&str> {
+        self.inner.url.as_deref()
+    }
+
+    /// Attaches a typed value to this error, to retrieve it with [`get`](Error::get) further up
+    /// the stack.
+    ///
+    /// Payloads carry the data a caller needs to recover from the error, such as a partially
+    /// parsed value, and are not rendered. They are shared between clones.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// struct Partial(Vec<u32>);
+    ///
+    /// fn parse(input: &str) -> Result<Vec<u32>> {
+    ///     let mut values = Vec::new();
+    ///     for part in input.split(',') {
+    ///         match part.parse() {
+    ///             Ok(value) => values.push(value),
+    ///             Err(e) => return Err(error!("invalid number").chain(e).with_payload(Partial(values))),
+    ///         }
+    ///     }
+    ///     Ok(values)
+    /// }
+    ///
+    /// let err = parse("1,2,x").context("failed to load").unwrap_err();
+    /// assert_eq!(err.get::<Partial>().unwrap().0, [1, 2]);
+    /// assert!(err.get::<String>().is_none());
+    /// ```
+    pub fn with_payload<T: Any + Send + Sync>(mut self, payload: T) -> Self {
+        self.inner.payloads.push(Arc::new(payload));
+        self
+    }
+
+    /// Returns the payload of type `T` attached to this error, the outermost one if there are
+    /// several, see [`with_payload`](Error::with_payload).
+    pub fn get<T: Any>(&self) -> Option<&T> {
+        self.inner
+            .payloads
+            .iter()
+            .rev()
+            .find_map(|payload| payload.downcast_ref())
+    }
+
+    /// Returns a value rendering this error with the given `theme`, regardless of whether colors
+    /// are enabled and of any report hook.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// let err = error!("oops");
+    /// assert!(!err.themed(Theme::PLAIN).to_string().contains('\x1b'));
+    /// ```
+    pub fn themed(&self, theme: Theme) -> Themed<'_> {
+        Themed { error: self, theme }
+    }
+
+    /// Returns a value rendering the messages of this error and its causes on a single line,
+    /// separated by `: `, without locations or styles.
+    ///
+    /// This is suited to structured log lines, where multi-line values are a problem.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// let err = error!("No such file").context("open /etc/app.toml").context("failed to load config");
+    /// assert_eq!(
+    ///     err.compact().to_string(),
+    ///     "failed to load config: open /etc/app.toml: No such file"
+    /// );
+    /// ```
+    pub fn compact(&self) -> Compact<'_> {
+        Compact(self)
+    }
+
+    /// Returns a value rendering this error as semantic HTML, for dashboards and emailed reports.
+    ///
+    /// The headline, causes, fields, notes, help messages and URL are rendered as elements with
+    /// `fu-` classes, such as `fu-message` and `fu-location`, and no inline styles. Nested errors
+    /// are flattened into the causes, and an error without a message, like a `?` conversion, is
+    /// headed by the first message of its chain. Text is escaped.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// let err = error!("inner").context("outer").with("path", "app.toml");
+    /// let html = err.html().to_string();
+    /// assert!(html.starts_with("<div class=\"fu-error\">\n<p class=\"fu-headline\"><span class=\"fu-message\">outer</span>"));
+    /// assert!(html.contains("<ol class=\"fu-causes\">\n<li><span class=\"fu-message\">inner</span>"));
+    /// assert!(html.contains("<dt>path</dt><dd>app.toml</dd>"));
+    /// ```
+    #[cfg(feature = "html")]
+    pub fn html(&self) -> Html<'_> {
+        Html(self)
+    }
+
+    /// Returns a value rendering this error as Markdown, to post it in issues and chats.
+    ///
+    /// The message is bold, the causes are a nested list, and the fields, notes, help messages
+    /// and URL follow in their own list. Locations are inline code spans rather than fenced
+    /// blocks, to stay on the line of their message. Nested errors are flattened into the
+    /// causes, and an error without a message, like a `?` conversion, is headed by the first
+    /// message of its chain. Text is escaped.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// let err = Error::new(Some("inner"), Location::new("src/main.rs", 3, 7)).context("outer");
+    /// let markdown = err.markdown().to_string();
+    /// # #[cfg(not(fu_no_location))]
+    /// assert!(markdown.starts_with("**outer** `"));
+    /// # #[cfg(not(fu_no_location))]
+    /// assert!(markdown.ends_with("\n\n- inner `src/main.rs:[3:7]`"));
+    /// ```
+    #[cfg(feature = "markdown")]
+    pub fn markdown(&self) -> Markdown<'_> {
+        Markdown(self)
+    }
+
+    /// Writes the report of this error to `writer` with the given `options`, regardless of any
+    /// report hook.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// let err = error!("inner").context("outer");
+    /// let mut out = Vec::new();
+    /// err.render_to(&mut out, &RenderOptions::new().color(false).verbose(true))
+    ///     .unwrap();
+    /// let out = String::from_utf8(out).unwrap();
+    /// # #[cfg(not(fu_no_location))]
+    /// assert!(out.starts_with("outer    "));
+    /// # #[cfg(not(fu_no_location))]
+    /// assert!(out.contains("\n  Caused by: inner    "));
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn render_to<W: std::io::Write>(
+        &self,
+        mut writer: W,
+        options: &RenderOptions,
+    ) -> std::io::Result<()> {
+        let rendered = render::Rendered {
+            error: self,
+            options,
+        };
+        write!(writer, "{}", rendered)
+    }
+
+    /// Renders this error deterministically, to compare it with a snapshot in tests.
+    ///
+    /// Locations, colors and anything else that changes between runs or refactors are left out,
+    /// see [`RenderOptions::snapshot`].
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// fn read() -> Result<String> {
+    ///     Err(std::io::Error::other("disk on fire"))?
+    /// }
+    /// let err = read().context("failed to load config").unwrap_err().with("path", "app.toml");
+    /// assert_eq!(
+    ///     err.to_snapshot_string(),
+    ///     "failed to load config\n  Caused by: disk on fire\n  path = app.toml"
+    /// );
+    /// ```
+    pub fn to_snapshot_string(&self) -> String {
+        let options = RenderOptions::snapshot();
+        let rendered = render::Rendered {
+            error: self,
+            options: &options,
+        };
+        rendered.to_string()
+    }
+
+    /// Prints the full report of this error to stderr, in the alternate format (`{:#}`).
+    ///
+    /// The report goes through the [report hook](set_report_hook) if any, and is colored if
+    /// [colors are enabled](color_enabled). The error is reported to `metrics` and Sentry.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// fn run() -> Result<()> {
+    ///     bail!("missing argument")
+    /// }
+    ///
+    /// if let Err(err) = run() {
+    ///     err.eprint();
+    /// }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn eprint(&self) {
+        self.reported();
+        eprintln!("{:#}", self);
+    }
+
+    /// Prints the full report of this error to stdout, like [`eprint`](Error::eprint).
+    ///
+    /// Colors follow [`color_enabled`], which is detected on stderr; disable them with
+    /// [`set_color`] when stdout is redirected.
+    #[cfg(feature = "std")]
+    pub fn print(&self) {
+        self.reported();
+        println!("{:#}", self);
+    }
+
+    /// Returns true if both errors have the same messages in their chain, outermost first,
+    /// regardless of where they were created.
+    ///
+    /// Sources are compared by their `Display` output.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// let a = error!("not found").context("failed to load");
+    /// let b = error!("not found").context("failed to load");
+    /// assert!(a.chain_eq(&b));
+    /// assert!(!a.chain_eq(&error!("failed to load")));
+    /// ```
+    pub fn chain_eq(&self, other: &Error) -> bool {
+        self.messages() == other.messages()
+    }
+
+    /// Returns the messages of the frames, outermost first, followed by those of the sources,
+    /// with the frames and sources of nested errors in place of their `Display`.
+    fn messages(&self) -> Vec<Cow<'_, str>> {
+        let mut messages = Vec::new();
+        self.push_messages(&mut messages);
+        messages
+    }
+
+    fn push_messages<'a>(&'a self, messages: &mut Vec<Cow<'a, str>>) {
+        let frames = self.inner.frames.iter().rev();
+        messages.extend(frames.filter_map(translate::message));
+        for cause in self.causes() {
+            match cause.downcast_ref::<Error>() {
+                Some(err) => err.push_messages(messages),
+                None => messages.extend(cause_message(cause)),
+            }
+        }
+    }
+
+    /// Returns true if the source of this error is of type `E`.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// let err = Error::from(std::io::Error::other("oops"));
+    /// assert!(err.is::<std::io::Error>());
+    /// assert!(!err.is::<std::fmt::Error>());
+    /// ```
+    pub fn is<E>(&self) -> bool
+    where
+        E: StdError + Send + Sync + 'static,
+    {
+        self.downcast_ref::<E>().is_some()
+    }
+
+    /// Returns a reference to the source of this error if it is of type `E`.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// let err = Error::from(std::io::Error::other("oops"));
+    /// let io = err.downcast_ref::<std::io::Error>().unwrap();
+    /// assert_eq!(io.kind(), std::io::ErrorKind::Other);
+    /// ```
+    pub fn downcast_ref<E>(&self) -> Option<&E>
+    where
+        E: StdError + Send + Sync + 'static,
+    {
+        Nested::first(self.inner.source.as_ref()?).downcast_ref::<E>()
+    }
+
+    /// Returns a mutable reference to the source of this error if it is of type `E`.
+    ///
+    /// Returns `None` if the source is shared with a clone of this error.
+    pub fn downcast_mut<E>(&mut self) -> Option<&mut E>
+    where
+        E: StdError + Send + Sync + 'static,
+    {
+        let mut source = Arc::get_mut(self.inner.source.as_mut()?)?;
+        if source.is::<Nested>() {
+            let nested = source.downcast_mut::<Nested>().unwrap();
+            source = Arc::get_mut(&mut nested.error)?;
+        }
+        source.downcast_mut::<E>()
+    }
+
+    /// Takes the source out of this error if it is of type `E`, otherwise gives the error
+    /// back unchanged.
+    ///
+    /// The error is also given back if the source is shared with a clone of this error.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// let err = Error::from(std::io::Error::other("oops"));
+    /// let err = err.downcast::<std::fmt::Error>().unwrap_err();
+    /// let io: std::io::Error = err.downcast().unwrap();
+    /// assert_eq!(io.to_string(), "oops");
+    /// ```
+    pub fn downcast<E>(mut self) -> core::result::Result<E, Self>
+    where
+        E: StdError + Send + Sync + 'static,
+    {
+        if !self.is::<E>() {
+            return Err(self);
+        }
+        let source = self.inner.source.take().unwrap();
+        if !source.is::<Nested>() {
+            return unwrap_arc(source).map_err(|source| {
+                self.inner.source = Some(source);
+                self
+            });
+        }
+        // Take the outermost source out, leaving the ones nested under it.
+        match unwrap_arc::<Nested>(source) {
+            Ok(Nested { error, next }) => match unwrap_arc(error) {
+                Ok(source) => {
+                    self.inner.source = Some(next);
+                    Ok(source)
+                }
+                Err(error) => {
+                    self.inner.source = Some(Arc::new(Nested { error, next }));
+                    Err(self)
+                }
+            },
+            Err(source) => {
+                self.inner.source = Some(source);
+                Err(self)
+            }
+        }
+    }
+
+    /// Returns an iterator over the frames of this error, outermost first.
+    ///
+    /// Every call to [`Error::context`] adds a frame, with its message and location. The causes
+    /// that are not an [`Error`] come after the frames, see [`Error::causes`].
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// let err = error!("inner").context("outer");
+    /// let frames: Vec<_> = err.frames().map(|frame| frame.message()).collect();
+    /// assert_eq!(frames, [Some("outer"), Some("inner")]);
+    /// # #[cfg(not(fu_no_location))]
+    /// assert_eq!(err.frames().last().unwrap().location().line(), line!() - 4);
+    /// ```
+    pub fn frames(&self) -> impl DoubleEndedIterator<Item = &Frame> + ExactSizeIterator {
+        self.inner.frames.iter().rev()
+    }
+
+    /// Returns an iterator over the causes of this error that are not frames, outermost first.
+    ///
+    /// Unlike walking [`StdError::source`], this includes every source nested by repeated
+    /// calls to [`Error::chain`].
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// let err = std::fs::read("does-not-exist").wrap("failed to load").unwrap_err();
+    /// let causes: Vec<_> = err.causes().collect();
+    /// assert_eq!(causes.len(), 1);
+    /// assert!(causes[0].is::<std::io::Error>());
+    /// ```
+    pub fn causes(&self) -> Causes<'_> {
+        Causes {
+            next: self
+                .inner
+                .source
+                .as_deref()
+                .map(|e| e as &(dyn StdError + 'static)),
+            pending: Vec::new(),
+            seen: Vec::new(),
+            truncated: false,
+        }
+    }
+}
+
+impl core::fmt::Debug for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+/// Converts an I/O error, or gives back the `Error` an I/O error was converted from unchanged.
+#[cfg(feature = "std")]
+impl From<std::io::Error> for Error {
+    #[cfg_attr(not(fu_no_location), track_caller)]
+    fn from(err: std::io::Error) -> Self {
+        if err.get_ref().is_some_and(|inner| inner.is::<Error>()) {
+            let inner = err
+                .into_inner()
+                .expect("the error was just checked to have an inner one");
+            return *inner
+                .downcast()
+                .expect("the error was just checked to be an `Error`");
+        }
+        Error::new_unreported(None, Location::caller()).chain(err)
+    }
+}
+
+/// Converts an `Error` into an I/O error of kind [`Other`](std::io::ErrorKind::Other), keeping
+/// it whole as the inner error, for APIs that require [`std::io::Error`].
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// use std::io::Read;
+///
+/// struct Source;
+///
+/// impl Read for Source {
+///     fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+///         Err(error!("upstream closed").into())
+///     }
+/// }
+///
+/// let io = Source.read(&mut [0; 8]).unwrap_err();
+/// assert_eq!(io.kind(), std::io::ErrorKind::Other);
+/// let err = io.get_ref().unwrap().downcast_ref::<Error>().unwrap();
+/// assert_eq!(err.message(), Some("upstream closed"));
+/// ```
+#[cfg(feature = "std")]
+impl From<Error> for std::io::Error {
+    fn from(err: Error) -> Self {
+        std::io::Error::other(err)
+    }
+}
+
+impl From<core::num::ParseIntError> for Error {
+    #[cfg_attr(not(fu_no_location), track_caller)]
+    fn from(err: core::num::ParseIntError) -> Self {
+        Error::new_unreported(None, Location::caller()).chain(err)
+    }
+}
+
+/// Creates an error with the given message, recording the location of the conversion.
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// fn check(name: &str) -> Result<()> {
+///     if name.is_empty() {
+///         return Err("name is empty".into());
+///     }
+///     Ok(())
+/// }
+/// assert_eq!(check("").unwrap_err().message(), Some("name is empty"));
+/// ```
+impl From<&str> for Error {
+    #[cfg_attr(not(fu_no_location), track_caller)]
+    fn from(message: &str) -> Self {
+        Error::new(Some(message.to_string()), Location::caller())
+    }
+}
+
+impl From<String> for Error {
+    #[cfg_attr(not(fu_no_location), track_caller)]
+    fn from(message: String) -> Self {
+        Error::new(Some(message), Location::caller())
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.inner
+            .source
+            .as_deref()
+            .map(|e| e as &(dyn StdError + 'static))
+    }
+
+    /// Provides the [`Location`], the backtrace, the [`ErrorKind`] and the [`Severity`] of this
+    /// error, and whatever its source provides.
+    ///
+    /// # Example
+    /// ```
+    /// #![feature(error_generic_member_access)]
+    /// # use fu::*;
+    /// let err = error!("oops");
+    /// let location = core::error::request_ref::<Location>(&err).unwrap();
+    /// assert_eq!(location.line(), line!() - 2);
+    /// ```
+    #[cfg(feature = "nightly")]
+    fn provide<'a>(&'a self, request: &mut core::error::Request<'a>) {
+        request.provide_ref::<Location>(&self.head().location);
+        #[cfg(feature = "backtrace")]
+        request.provide_ref::<std::backtrace::Backtrace>(&self.inner.backtrace);
+        if let Some(kind) = self.kind() {
+            request.provide_value::<ErrorKind>(kind);
+        }
+        request.provide_value::<Severity>(self.severity());
+        if let Some(source) = &self.inner.source {
+            source.provide(request);
+        }
+    }
+}
+
+/// Takes the value out of `source` if it is an `E` that is not shared.
+fn unwrap_arc<E>(
+    source: Arc<dyn StdError + Send + Sync>,
+) -> core::result::Result<E, Arc<dyn StdError + Send + Sync>>
+where
+    E: StdError + Send + Sync + 'static,
+{
+    if !source.is::<E>() {
+        return Err(source);
+    }
+    // SAFETY: the source was just checked to be an `E`, so the allocation behind the `Arc`
+    // holds an `E`, the same way `Arc<dyn Any>::downcast` works.
+    let source = unsafe { Arc::from_raw(Arc::into_raw(source).cast::<E>()) };
+    Arc::try_unwrap(source).map_err(|source| source as Arc<dyn StdError + Send + Sync>)
+}
+
+/// A source with another one nested under it, built by repeated calls to [`Error::chain`].
+///
+/// Only the end of the chain of `error` can lead on to `next`, so walking [`StdError::source`]
+/// skips `next` if `error` has causes of its own. [`Causes`] walks both.
+struct Nested {
+    error: Arc<dyn StdError + Send + Sync>,
+    next: Arc<dyn StdError + Send + Sync>,
+}
+
+impl Nested {
+    /// Nests `source` under the innermost of the `existing` sources.
+    fn push(
+        existing: Arc<dyn StdError + Send + Sync>,
+        source: Arc<dyn StdError + Send + Sync>,
+    ) -> Arc<dyn StdError + Send + Sync> {
+        match existing.downcast_ref::<Nested>() {
+            Some(nested) => Arc::new(Nested {
+                error: nested.error.clone(),
+                next: Nested::push(nested.next.clone(), source),
+            }),
+            None => Arc::new(Nested {
+                error: existing,
+                next: source,
+            }),
+        }
+    }
+
+    /// Returns the outermost of the sources.
+    fn first(source: &Arc<dyn StdError + Send + Sync>) -> &Arc<dyn StdError + Send + Sync> {
+        match source.downcast_ref::<Nested>() {
+            Some(nested) => &nested.error,
+            None => source,
+        }
+    }
+}
+
+impl core::fmt::Display for Nested {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Display::fmt(&self.error, f)
+    }
+}
+
+impl core::fmt::Debug for Nested {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Debug::fmt(&self.error, f)
+    }
+}
+
+impl StdError for Nested {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self.error.source() {
+            Some(source) => Some(source),
+            None => Some(&*self.next),
+        }
+    }
+}
+
+static MAX_DEPTH: core::sync::atomic::AtomicUsize = core::sync::atomic::AtomicUsize::new(128);
+
+/// Sets the maximum number of causes walked by [`Error::causes`], and so rendered, globally.
+///
+/// Walking also stops at a cause that was already seen, when the sources of a badly written
+/// error form a cycle. The default is 128.
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// fu::set_max_depth(1);
+/// let io = std::io::Error::other("disk on fire");
+/// let err = error!("failed to save").chain(io).chain(std::fmt::Error);
+/// assert_eq!(err.causes().count(), 1);
+/// assert!(err.to_string().contains("(further causes omitted)"));
+/// ```
+pub fn set_max_depth(depth: usize) {
+    MAX_DEPTH.store(depth, core::sync::atomic::Ordering::Relaxed);
+}
+
+/// An iterator over the causes of an [`Error`], see [`Error::causes`].
+pub struct Causes<'a> {
+    next: Option<&'a (dyn StdError + 'static)>,
+    /// Sources nested under the chain currently walked.
+    pending: Vec<&'a (dyn StdError + 'static)>,
+    /// The causes returned so far.
+    seen: Vec<&'a (dyn StdError + 'static)>,
+    truncated: bool,
+}
+
+impl<'a> Causes<'a> {
+    /// Returns true if walking stopped early, at the [maximum depth](set_max_depth) or at a
+    /// cycle, leaving causes out.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    fn stop(&mut self) -> Option<&'a (dyn StdError + 'static)> {
+        self.truncated = true;
+        self.next = None;
+        self.pending.clear();
+        None
+    }
+}
+
+impl<'a> Iterator for Causes<'a> {
+    type Item = &'a (dyn StdError + 'static);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut err = self.next.take().or_else(|| self.pending.pop())?;
+        while let Some(nested) = err.downcast_ref::<Nested>() {
+            self.pending.push(&*nested.next);
+            err = &*nested.error;
+        }
+        let max_depth = MAX_DEPTH.load(core::sync::atomic::Ordering::Relaxed);
+        if self.seen.len() >= max_depth || self.seen.iter().any(|seen| is_same(*seen, err)) {
+            return self.stop();
+        }
+        self.seen.push(err);
+        // The rendering of an `Error` already includes its own causes.
+        self.next = if err.is::<Error>() {
+            None
+        } else {
+            err.source()
+        };
+        Some(err)
+    }
+}
+
+/// Returns true if `a` and `b` are the same error, by identity.
+///
+/// Vtables can be duplicated, so the data addresses are compared. A source stored at the start
+/// of the error holding it has the same address but usually a smaller size, and when it has the
+/// same size, as in a newtype, another message.
+fn is_same(a: &(dyn StdError + 'static), b: &(dyn StdError + 'static)) -> bool {
+    let address = |err: &(dyn StdError + 'static)| (err as *const dyn StdError).cast::<()>();
+    if address(a) != address(b) || core::mem::size_of_val(a) != core::mem::size_of_val(b) {
+        return false;
+    }
+    #[allow(ambiguous_wide_pointer_comparisons)]
+    let same_vtable = core::ptr::eq(a, b);
+    same_vtable || a.to_string() == b.to_string()
+}
+
+/// The value rendered in place of a secret field, see [`Error::with_secret`].
+const REDACTED: &str = "[REDACTED]";
+
+/// Returns the message of a cause, as rendered after those of the frames of an error. Only a
+/// frame deserialized without a message has none.
+pub(crate) fn cause_message<'a>(cause: &'a (dyn StdError + 'static)) -> Option<Cow<'a, str>> {
+    #[cfg(feature = "serde")]
+    if let Some(remote) = cause.downcast_ref::<DeserializedError>() {
+        // Leave out the location rendered by its `Display` implementation.
+        return remote.message().map(Cow::Borrowed);
+    }
+    Some(match cause.downcast_ref::<ErrorGroup>() {
+        Some(group) => Cow::Owned(group.compact_message()),
+        None => Cow::Owned(cause.to_string()),
+    })
+}
+
+/// Returns true if retrying may succeed according to a cause, see [`Error::is_transient`].
+fn is_transient_cause(cause: &(dyn StdError + 'static)) -> bool {
+    if let Some(err) = cause.downcast_ref::<Error>() {
+        return err.is_transient();
+    }
+    #[cfg(feature = "std")]
+    if let Some(err) = cause.downcast_ref::<std::io::Error>() {
+        use std::io::ErrorKind::*;
+        return matches!(
+            err.kind(),
+            TimedOut | Interrupted | WouldBlock | ConnectionReset | ConnectionAborted
+        );
+    }
+    false
+}
+
+/// A message formatted the first time it is used, see [`Error::lazy`].
+#[cfg(feature = "std")]
+struct LazyMessage {
+    format: Box<dyn Fn() -> String + Send + Sync>,
+    message: std::sync::OnceLock<String>,
+}
+
+#[cfg(feature = "std")]
+impl LazyMessage {
+    fn get(&self) -> &str {
+        self.message.get_or_init(&self.format)
+    }
+}
+
+#[cfg(feature = "std")]
+impl core::fmt::Debug for LazyMessage {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("LazyMessage").field(&self.get()).finish()
+    }
+}
+
+/// A single layer of context: an optional message and the location it was attached at.
+///
+/// See [`Error::frames`].
+#[derive(Debug, Clone)]
+pub struct Frame {
+    message: Option<Cow<'static, str>>,
+    location: Location,
+    key: Option<Box<MessageKey>>,
+    /// The message of errors created with `Error::lazy`, formatted on first use.
+    #[cfg(feature = "std")]
+    lazy: Option<Arc<LazyMessage>>,
+}
+
+impl Frame {
+    fn new(message: Option<Cow<'static, str>>, location: Location) -> Self {
+        Frame {
+            message,
+            location,
+            key: None,
+            #[cfg(feature = "std")]
+            lazy: None,
+        }
+    }
+
+    /// Returns the message of this frame, if any.
+    pub fn message(&self) -> Option<&str> {
+        #[cfg(feature = "std")]
+        if let Some(lazy) = &self.lazy {
+            return Some(lazy.get());
+        }
+        self.message.as_deref()
+    }
+
+    /// Returns the location this frame was attached at.
+    pub fn location(&self) -> Location {
+        self.location
+    }
+
+    /// Returns the message key of this frame, if any, see [`Error::with_message_key`].
+    pub fn message_key(&self) -> Option<&MessageKey> {
+        self.key.as_deref()
+    }
+}
+
+#[doc(hidden)]
+pub mod __private {
+    use alloc::borrow::Cow;
+    use core::error::Error as StdError;
+
+    use crate::{Error, Location};
+
+    /// Returns the path of the function from the type name of an item `f` defined in it, see
+    /// `__function!`.
+    pub fn function_name(mut name: &'static str) -> &'static str {
+        name = name.strip_suffix("::f").unwrap_or(name);
+        while let Some(outer) = name.strip_suffix("::{{closure}}") {
+            name = outer;
+        }
+        name
+    }
+
+    /// Formats a macro message, without allocating if it is a plain string literal.
+    pub fn format_message(args: core::fmt::Arguments<'_>) -> Cow<'static, str> {
+        match args.as_str() {
+            Some(message) => Cow::Borrowed(message),
+            None => Cow::Owned(alloc::fmt::format(args)),
+        }
+    }
+
+    // The constructors called by `error!` are outlined and cold, so that the many call sites of
+    // `bail!` and `ensure!` stay small and the code around them is laid out for the happy path.
+
+    /// Creates an error with a message formatted from `args`.
+    #[cold]
+    #[inline(never)]
+    pub fn error(args: core::fmt::Arguments<'_>, location: Location) -> Error {
+        Error::new(Some(format_message(args)), location)
+    }
+
+    /// Creates an error without a message.
+    #[cold]
+    #[inline(never)]
+    pub fn error_empty(location: Location) -> Error {
+        Error::new(None::<&'static str>, location)
+    }
+
+    /// Creates an error without a message, with `source` as its source.
+    #[cold]
+    #[inline(never)]
+    pub fn error_chained<E>(source: E, location: Location) -> Error
+    where
+        E: StdError + Send + Sync + 'static,
+    {
+        Error::new_unreported(None, location).chain(source)
+    }
+
+    /// Creates the error of `ensure!` from an expression that is not a format string.
+    #[cold]
+    #[inline(never)]
+    pub fn ensure_error<M: EnsureError>(value: M, location: Location) -> Error {
+        value.into_error(location)
+    }
+
+    /// A value `ensure!` can fail with: a message, or an [`Error`] returned as is.
+    pub trait EnsureError {
+        fn into_error(self, location: Location) -> Error;
+    }
+
+    impl EnsureError for Error {
+        fn into_error(self, _location: Location) -> Error {
+            self
+        }
+    }
+
+    impl EnsureError for &'static str {
+        fn into_error(self, location: Location) -> Error {
+            Error::new(Some(self), location)
+        }
+    }
+
+    impl EnsureError for alloc::string::String {
+        fn into_error(self, location: Location) -> Error {
+            Error::new(Some(self), location)
+        }
+    }
+
+    impl EnsureError for &alloc::string::String {
+        fn into_error(self, location: Location) -> Error {
+            Error::new(Some(self.clone()), location)
+        }
+    }
+
+    impl EnsureError for Cow<'static, str> {
+        fn into_error(self, location: Location) -> Error {
+            Error::new(Some(self), location)
+        }
+    }
+
+    /// Creates an error with a message formatted by `format` when it is first used.
+    #[cfg(feature = "std")]
+    #[cold]
+    #[inline(never)]
+    pub fn error_lazy(
+        format: alloc::boxed::Box<dyn Fn() -> alloc::string::String + Send + Sync>,
+        location: Location,
+    ) -> Error {
+        Error::new_lazy(format, location)
+    }
+
+    /// Returns a `#[source]` field of a `#[derive(FuError)]` type as a trait object. Unlike a
+    /// cast, it also accepts boxed trait objects, which are not themselves errors.
+    pub trait AsDynError<'a> {
+        fn as_dyn_error(&self) -> &(dyn StdError + 'a);
+    }
+
+    impl<'a, E: StdError + 'a> AsDynError<'a> for E {
+        fn as_dyn_error(&self) -> &(dyn StdError + 'a) {
+            self
+        }
+    }
+
+    impl<'a> AsDynError<'a> for dyn StdError + 'a {
+        fn as_dyn_error(&self) -> &(dyn StdError + 'a) {
+            self
+        }
+    }
+
+    impl<'a> AsDynError<'a> for dyn StdError + Send + 'a {
+        fn as_dyn_error(&self) -> &(dyn StdError + 'a) {
+            self
+        }
+    }
+
+    impl<'a> AsDynError<'a> for dyn StdError + Send + Sync + 'a {
+        fn as_dyn_error(&self) -> &(dyn StdError + 'a) {
+            self
+        }
+    }
+
+    /// Renders every frame of `err` without styles, see `assert_err!`.
+    pub fn render_plain(err: &crate::Error) -> alloc::string::String {
+        alloc::format!("{:#}", err.themed(crate::Theme::PLAIN))
+    }
+}
+
+/// [`Result`](core::result::Result)<T, E>, with [`Error`] as the default error type.
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// #[derive(Debug)]
+/// struct ParseError;
+///
+/// fn parse(input: &str) -> fu::Result<u32, ParseError> {
+///     input.parse().map_err(|_| ParseError)
+/// }
+///
+/// fn load(input: &str) -> fu::Result<u32> {
+///     parse(input).map_err(|_| error!("invalid input: {}", input))
+/// }
+/// assert!(load("x").is_err());
+/// ```
+pub type Result<T, E = Error> = core::result::Result<T, E>;
+
+/// Construct a Result with the crates [`Error`] type.
+///
+/// This macro uses the `file!()`, `line!()`, and `column!()` macros to automatically
+/// capture the file and location of the error.
+///
+/// Given a single expression that is not a string literal, the expression must be an error
+/// value which is kept as the source of the new error, see [`Error::chain`].
+///
+/// The arguments can be prefixed with `code = "..."` to set the [code](Error::with_code) of the
+/// error, and with `lazy` to only format the message when it is first used, see [`Error::lazy`].
+/// The arguments of a `lazy` message are moved into a closure.
+///
+/// A message without arguments is kept as a `&'static str`, without formatting or allocating it.
+///
+/// A message can be followed by `; key = value, ...` to attach [fields](Error::with) instead of
+/// interpolating dynamic data into the message, which then stays the same for every occurrence.
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// let result: Result<()> = Err(error!("oops"));
+/// assert!(result.is_err());
+///
+/// let io = std::io::Error::other("disk on fire");
+/// let err = error!(io);
+/// assert!(err.is::<std::io::Error>());
+///
+/// let err = error!(code = "E1023", "invalid token {:?}", "}");
+/// assert_eq!(err.code(), Some("E1023"));
+///
+/// let err = error!("user not found"; user_id = 42, region = "eu");
+/// assert_eq!(err.message(), Some("user not found"));
+/// assert_eq!(err.fields().collect::<Vec<_>>(), [("user_id", "42"), ("region", "eu")]);
+/// ```
+#[macro_export]
+macro_rules! error {
+    (code = $code:expr, $($arg:tt)*) => {
+        $crate::error!($($arg)*).with_code($code)
+    };
+    ($fmt:literal $(, $arg:expr)* ; $($key:ident = $value:expr),+ $(,)?) => {
+        $crate::error!($fmt $(, $arg)*)$(.with(stringify!($key), $value))+
+    };
+    (lazy $fmt:literal $($arg:tt)*) => {
+        $crate::__private::error_lazy(
+            ::std::boxed::Box::new(move || ::std::format!($fmt $($arg)*)),
+            $crate::__location!(),
+        )
+    };
+    () => {
+        $crate::__private::error_empty($crate::__location!())
+    };
+    ($fmt:literal $($arg:tt)*) => {
+        $crate::__private::error(format_args!($fmt $($arg)*), $crate::__location!())
+    };
+    ($err:expr $(,)?) => {
+        $crate::__private::error_chained($err, $crate::__location!())
+    };
+}
+
+/// Return early with an error.
+///
+/// This macro behaves like the [`error!`] macro but immediately returns the error from the
+/// function.
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// fn example() -> Result<()> {
+///     bail!("an early exit error")
+/// }
+/// assert!(example().is_err());
+///
+/// fn forward(err: std::io::Error) -> Result<()> {
+///     bail!(err)
+/// }
+/// assert!(forward(std::io::Error::other("oops")).unwrap_err().is::<std::io::Error>());
+/// ```
+///
+/// The error is converted with [`Into`], so the macro also works in functions returning any
+/// error type that implements `From<fu::Error>`, as does [`ensure!`].
+///
+/// ```
+/// # use fu::*;
+/// #[derive(Debug)]
+/// enum AppError {
+///     Internal(Error),
+/// }
+///
+/// impl From<Error> for AppError {
+///     fn from(err: Error) -> Self {
+///         AppError::Internal(err)
+///     }
+/// }
+///
+/// fn check(value: i32) -> core::result::Result<(), AppError> {
+///     ensure!(value >= 0, "value must be non-negative");
+///     bail!("not implemented")
+/// }
+/// assert!(matches!(check(-1), Err(AppError::Internal(_))));
+/// ```
+#[macro_export]
+macro_rules! bail {
+    ($($arg:tt)*) => {
+        return Err($crate::error!($($arg)*).into())
+    };
+}
+
+/// Return early with an error if a condition is not satisfied.
+///
+/// If the provided condition is not met, this macro will trigger a [`bail!`] with the given
+/// error message. It is a convenient way to validate inputs.
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// fn check(value: i32) -> Result<()> {
+///     ensure!(value >= 0, "value must be non-negative");
+///     Ok(())
+/// }
+/// assert!(check(-1).is_err());
+/// ```
+///
+/// Without a message, the error describes the condition that failed.
+///
+/// ```
+/// # use fu::*;
+/// fn check(value: i32) -> Result<()> {
+///     ensure!(value >= 0);
+///     Ok(())
+/// }
+/// assert!(check(-1).unwrap_err().to_string().contains("Condition failed: `value >= 0`"));
+/// ```
+///
+/// Instead of a format string, the message can be any expression evaluating to a string, or an
+/// [`Error`] to return as is.
+///
+/// ```
+/// # use fu::*;
+/// fn check(value: i32, message: String) -> Result<()> {
+///     ensure!(value < 10, message);
+///     ensure!(value >= 0, error!("value is negative").with_kind(ErrorKind::InvalidInput));
+///     Ok(())
+/// }
+/// assert_eq!(check(42, "too big".into()).unwrap_err().message(), Some("too big"));
+/// assert_eq!(check(-1, "too big".into()).unwrap_err().kind(), Some(ErrorKind::InvalidInput));
+/// ```
+#[macro_export]
+macro_rules! ensure {
+    ($condition:expr $(,)?) => {
+        if !($condition) {
+            $crate::bail!("Condition failed: `{}`", stringify!($condition));
+        }
+    };
+    ($condition:expr, code = $($arg:tt)*) => {
+        if !($condition) {
+            $crate::bail!(code = $($arg)*);
+        }
+    };
+    ($condition:expr, lazy $($arg:tt)*) => {
+        if !($condition) {
+            $crate::bail!(lazy $($arg)*);
+        }
+    };
+    ($condition:expr, $fmt:literal $($arg:tt)*) => {
+        if !($condition) {
+            $crate::bail!($fmt $($arg)*);
+        }
+    };
+    ($condition:expr, $err:expr $(,)?) => {
+        if !($condition) {
+            return Err($crate::__private::ensure_error($err, $crate::__location!()).into());
+        }
+    };
+}
+
+/// Return early with an error if a condition is satisfied.
+///
+/// This is the inverse of [`ensure!`], with the same arguments, and reads better for guard
+/// clauses.
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// # use std::path::Path;
+/// fn create(path: &Path) -> Result<()> {
+///     bail_if!(path.exists(), "refusing to overwrite {}", path.display());
+///     Ok(())
+/// }
+/// let err = create(Path::new("Cargo.toml")).unwrap_err();
+/// assert_eq!(err.message(), Some("refusing to overwrite Cargo.toml"));
+/// ```
+///
+/// Without a message, the error describes the condition that was satisfied.
+///
+/// ```
+/// # use fu::*;
+/// fn check(value: i32) -> Result<()> {
+///     bail_if!(value < 0);
+///     Ok(())
+/// }
+/// assert!(check(-1).unwrap_err().to_string().contains("Condition failed: `!(value < 0)`"));
+/// ```
+#[macro_export]
+macro_rules! bail_if {
+    ($condition:expr $(,)?) => {
+        if $condition {
+            $crate::bail!("Condition failed: `!({})`", stringify!($condition));
+        }
+    };
+    ($condition:expr, $($arg:tt)+) => {
+        $crate::ensure!(!($condition), $($arg)+)
+    };
+}
+
+/// Unwrap an [`Option`], or return early with an error if it is `None`.
+///
+/// The macro evaluates to the value inside `Some`, the arguments after the option are those of
+/// [`bail!`].
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// # use std::collections::HashMap;
+/// fn port(config: &HashMap<&str, u16>, name: &str) -> Result<u16> {
+///     let port = ensure_some!(config.get(name), "missing port for {}", name);
+///     Ok(*port)
+/// }
+/// let config = HashMap::from([("http", 80)]);
+/// assert_eq!(port(&config, "http").unwrap(), 80);
+/// assert_eq!(port(&config, "ftp").unwrap_err().message(), Some("missing port for ftp"));
+/// ```
+///
+/// Without a message, the error describes the option that was `None`.
+///
+/// ```
+/// # use fu::*;
+/// fn first(values: &[i32]) -> Result<i32> {
+///     Ok(*ensure_some!(values.first()))
+/// }
+/// assert!(first(&[]).unwrap_err().to_string().contains("Condition failed: `values.first()` is `None`"));
+/// ```
+#[macro_export]
+macro_rules! ensure_some {
+    ($option:expr $(,)?) => {
+        match $option {
+            ::core::option::Option::Some(value) => value,
+            ::core::option::Option::None => {
+                $crate::bail!("Condition failed: `{}` is `None`", stringify!($option))
+            }
+        }
+    };
+    ($option:expr, $($arg:tt)+) => {
+        match $option {
+            ::core::option::Option::Some(value) => value,
+            ::core::option::Option::None => $crate::bail!($($arg)+),
+        }
+    };
+}
+
+/// Return early with an error if two expressions are not equal.
+///
+/// Like [`assert_eq!`], the error message contains the `Debug` representation of both values.
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// fn check(len: usize) -> Result<()> {
+///     ensure_eq!(len, 4, "unexpected length");
+///     Ok(())
+/// }
+/// let err = check(3).unwrap_err();
+/// assert!(err.to_string().contains("unexpected length (left: 3, right: 4)"));
+/// ```
+#[macro_export]
+macro_rules! ensure_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if !(*left == *right) {
+                    $crate::bail!(
+                        "Condition failed: `{} == {}` (left: {:?}, right: {:?})",
+                        stringify!($left),
+                        stringify!($right),
+                        left,
+                        right,
+                    );
+                }
+            }
+        }
+    };
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if !(*left == *right) {
+                    $crate::bail!("{} (left: {:?}, right: {:?})", format_args!($($arg)+), left, right);
+                }
+            }
+        }
+    };
+}
+
+/// Return early with an error if two expressions are equal.
+///
+/// Like [`assert_ne!`], the error message contains the `Debug` representation of both values.
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// fn check(a: &str, b: &str) -> Result<()> {
+///     ensure_ne!(a, b);
+///     Ok(())
+/// }
+/// let err = check("x", "x").unwrap_err();
+/// assert!(err.to_string().contains("Condition failed: `a != b` (left: \"x\", right: \"x\")"));
+/// ```
+#[macro_export]
+macro_rules! ensure_ne {
+    ($left:expr, $right:expr $(,)?) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if *left == *right {
+                    $crate::bail!(
+                        "Condition failed: `{} != {}` (left: {:?}, right: {:?})",
+                        stringify!($left),
+                        stringify!($right),
+                        left,
+                        right,
+                    );
+                }
+            }
+        }
+    };
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if *left == *right {
+                    $crate::bail!("{} (left: {:?}, right: {:?})", format_args!($($arg)+), left, right);
+                }
+            }
+        }
+    };
+}
+
+/// Return early with an error if an expression does not match a pattern.
+///
+/// Like `assert_matches!`, the error message contains the `Debug` representation of the value.
+/// The pattern can be followed by an `if` guard.
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// #[derive(Debug)]
+/// enum State {
+///     Idle,
+///     Running(u32),
+/// }
+///
+/// fn stop(state: State) -> Result<()> {
+///     ensure_matches!(state, State::Running(_), "cannot stop");
+///     Ok(())
+/// }
+///
+/// assert!(stop(State::Running(1)).is_ok());
+/// let err = stop(State::Idle).unwrap_err();
+/// assert!(err.to_string().contains("cannot stop (value: Idle)"));
+/// ```
+#[macro_export]
+macro_rules! ensure_matches {
+    ($expression:expr, $pattern:pat $(if $guard:expr)? $(,)?) => {
+        match &$expression {
+            value => {
+                if !matches!(value, $pattern $(if $guard)?) {
+                    $crate::bail!(
+                        "Condition failed: `{}` does not match `{}` (value: {:?})",
+                        stringify!($expression),
+                        stringify!($pattern $(if $guard)?),
+                        value,
+                    );
+                }
+            }
+        }
+    };
+    ($expression:expr, $pattern:pat $(if $guard:expr)?, $($arg:tt)+) => {
+        match &$expression {
+            value => {
+                if !matches!(value, $pattern $(if $guard)?) {
+                    $crate::bail!("{} (value: {:?})", format_args!($($arg)+), value);
+                }
+            }
+        }
+    };
+}
+
+/// Defines an error enum with a message for every variant, a lightweight alternative to
+/// `#[derive(FuError)]`.
+///
+/// Every variant is a unit or has named fields, and is followed by `=>` and its message, which
+/// can refer to the fields by name. A message with additional format arguments is written in
+/// parentheses, as `("...", args...)`. A field named
+/// `source` is returned by `Error::source`. The macro derives `Debug` and implements `Display`,
+/// `std::error::Error` and `From<T> for fu::Error`, which records the location of the
+/// conversion.
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// define_error! {
+///     /// Errors of the config loader.
+///     pub enum ConfigError {
+///         NotFound { path: String } => "config file {path} not found",
+///         InvalidPort { port: u16 } => ("invalid port {}", port + 1),
+///         Io { source: std::io::Error } => "failed to read config",
+///         Empty => "config is empty",
+///     }
+/// }
+///
+/// fn load() -> Result<()> {
+///     Err(ConfigError::NotFound { path: "app.toml".into() })?
+/// }
+///
+/// let err = load().unwrap_err();
+/// assert!(err.is::<ConfigError>());
+/// # #[cfg(not(fu_no_location))]
+/// assert_eq!(err.location().line(), line!() - 6);
+/// assert!(err.to_string().starts_with("config file app.toml not found"));
+///
+/// # use std::error::Error as _;
+/// let io = ConfigError::Io { source: std::io::Error::other("disk on fire") };
+/// assert_eq!(io.source().unwrap().to_string(), "disk on fire");
+/// ```
+#[macro_export]
+macro_rules! define_error {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $(
+                $(#[$variant_meta:meta])*
+                $variant:ident $({ $($field:ident : $ty:ty),* $(,)? })? => $message:tt
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug)]
+        $vis enum $name {
+            $(
+                $(#[$variant_meta])*
+                $variant $({ $($field: $ty),* })?,
+            )*
+        }
+
+        impl ::core::fmt::Display for $name {
+            #[allow(unused_variables)]
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                match self {
+                    $(Self::$variant { $($($field),*)? } => $crate::__error_write!(f, $message),)*
+                }
+            }
+        }
+
+        impl ::core::error::Error for $name {
+            #[allow(unused_variables)]
+            fn source(&self) -> ::core::option::Option<&(dyn ::core::error::Error + 'static)> {
+                match self {
+                    $(Self::$variant { $($($field),*)? } => $crate::__error_source!($($($field),*)?),)*
+                }
+            }
+        }
+
+        impl ::core::convert::From<$name> for $crate::Error {
+            // The cfg is checked by fu, it is unknown to the crate calling the macro.
+            $crate::__track_caller! {
+                fn from(err: $name) -> Self {
+                    $crate::__private::error_chained(err, $crate::Location::caller())
+                }
+            }
+        }
+    };
+}
+
+/// Writes a message of `define_error!`, a literal or a parenthesized list of format arguments.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __error_write {
+    ($f:ident, ($($arg:tt)*)) => {
+        ::core::write!($f, $($arg)*)
+    };
+    ($f:ident, $fmt:literal) => {
+        ::core::write!($f, $fmt)
+    };
+}
+
+/// Expands to the field named `source` among the given bindings, see `define_error!`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __error_source {
+    (@check source $binding:ident; $($rest:ident),*) => {
+        ::core::option::Option::Some($binding as &(dyn ::core::error::Error + 'static))
+    };
+    (@check $other:ident $binding:ident; $($rest:ident),*) => {
+        $crate::__error_source!($($rest),*)
+    };
+    () => {
+        ::core::option::Option::None
+    };
+    // The name is matched against `source`, the binding keeps the hygiene of the caller.
+    ($field:ident $(, $rest:ident)*) => {
+        $crate::__error_source!(@check $field $field; $($rest),*)
+    };
+}
+
+/// Asserts that a [`Result`] is an error, and evaluates to the error.
+///
+/// The error can also be checked against a predicate, in which case the full chain of the error
+/// is shown if it does not match. Like [`assert!`], this is meant for tests.
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// fn parse(s: &str) -> Result<i32> {
+///     s.parse::<i32>().wrap("not a number")
+/// }
+///
+/// let err = assert_err!(parse("x"));
+/// assert_eq!(err.message(), Some("not a number"));
+/// assert_err!(parse("x"), |err: &Error| err.is::<std::num::ParseIntError>());
+/// ```
+#[macro_export]
+macro_rules! assert_err {
+    ($result:expr $(,)?) => {
+        match $result {
+            ::core::result::Result::Ok(value) => ::core::panic!(
+                "assertion failed: `{}` is an error\n  got: Ok({:?})",
+                stringify!($result),
+                value,
+            ),
+            ::core::result::Result::Err(err) => err,
+        }
+    };
+    ($result:expr, $predicate:expr $(,)?) => {{
+        let err = $crate::assert_err!($result);
+        if !($predicate)(&err) {
+            ::core::panic!(
+                "assertion failed: the error of `{}` matches `{}`\n  error: {}",
+                stringify!($result),
+                stringify!($predicate),
+                $crate::__private::render_plain(&err),
+            );
+        }
+        err
+    }};
+}
+
+/// Asserts that a [`Result`] is an error whose full chain contains the given substring, and
+/// evaluates to the error.
+///
+/// The chain is rendered in the alternate format (`{:#}`), without styles.
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// fn load() -> Result<String> {
+///     std::fs::read_to_string("does-not-exist").wrap("failed to load config")
+/// }
+///
+/// assert_err_contains!(load(), "failed to load config");
+/// assert_err_contains!(load(), "No such file");
+/// ```
+#[macro_export]
+macro_rules! assert_err_contains {
+    ($result:expr, $needle:expr $(,)?) => {{
+        let err = $crate::assert_err!($result);
+        let rendered = $crate::__private::render_plain(&err);
+        if !rendered.contains($needle) {
+            ::core::panic!(
+                "assertion failed: the error of `{}` contains {:?}\n  error: {}",
+                stringify!($result),
+                $needle,
+                rendered,
+            );
+        }
+        err
+    }};
+}
+
+/// Attach context to a fallible value, turning it into a [`Result`].
+///
+/// Implemented for [`std::result::Result`] and [`Option`]. The location recorded in the
+/// resulting [`Error`] is the place where `context` was called, and the original error, if any,
+/// is kept as its source. The names are those used by `anyhow`, and the context can be any
+/// [`Display`](core::fmt::Display) value, so code migrating from it can keep its `.context(...)`
+/// and `.with_context(|| ...)` calls. [`Wrap`] provides the same operations under the original
+/// names of this crate.
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// fn read_config(path: &std::path::Path) -> Result<String> {
+///     std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))
+/// }
+/// let err = read_config("missing.toml".as_ref()).unwrap_err();
+/// assert_eq!(err.message(), Some("failed to read missing.toml"));
+///
+/// let port: Result<&str> = None.context("missing port");
+/// assert!(port.is_err());
+/// ```
+pub trait Context<T>: Sized {
+    /// Wrap the error value with a context message.
+    #[cfg_attr(not(fu_no_location), track_caller)]
+    fn context<C: core::fmt::Display>(self, context: C) -> Result<T> {
+        self.wrap_at(
+            || Some(__private::format_message(format_args!("{}", context))),
+            Location::caller(),
+        )
+    }
+
+    /// Wrap the error value with a context message that is only evaluated if an error occurred.
+    #[cfg_attr(not(fu_no_location), track_caller)]
+    fn with_context<C, F>(self, f: F) -> Result<T>
+    where
+        C: core::fmt::Display,
+        F: FnOnce() -> C,
+    {
+        self.wrap_at(
+            || Some(__private::format_message(format_args!("{}", f()))),
+            Location::caller(),
+        )
+    }
+
+    /// Wrap the error value with the message returned by `message`, if any, at `location`. The
+    /// other methods of this trait and of [`Wrap`] are built on it.
+    #[doc(hidden)]
+    fn wrap_at<F>(self, message: F, location: Location) -> Result<T>
+    where
+        F: FnOnce() -> Option<Cow<'static, str>>;
+}
+
+impl<T, E> Context<T> for core::result::Result<T, E>
+where
+    E: StdError + Send + Sync + 'static,
+{
+    fn wrap_at<F>(self, message: F, location: Location) -> Result<T>
+    where
+        F: FnOnce() -> Option<Cow<'static, str>>,
+    {
+        match self {
+            Ok(value) => Ok(value),
+            Err(err) => Err(Error::new_unreported(message(), location).chain(err)),
+        }
+    }
 }
 
-/// Return early with an error.
-///
-/// This macro behaves like the [`error!`] macro but immediately returns the error from the
-/// function.
+/// Turns `None` into an [`Error`] with the given context and the caller's location.
 ///
 /// # Example
 /// ```
 /// # use fu::*;
-/// fn example() -> Result<()> {
-///     bail!("an early exit error")
-/// }
-/// assert!(example().is_err());
+/// let map = std::collections::HashMap::<&str, i32>::new();
+/// let result = map.get("key").context("missing key");
+/// assert!(result.is_err());
 /// ```
-#[macro_export]
-macro_rules! bail {
-    ($($arg:tt)*) => {
-        return Err($crate::error!($($arg)*))
-    };
+impl<T> Context<T> for Option<T> {
+    fn wrap_at<F>(self, message: F, location: Location) -> Result<T>
+    where
+        F: FnOnce() -> Option<Cow<'static, str>>,
+    {
+        match self {
+            Some(value) => Ok(value),
+            None => Err(Error::new(message(), location)),
+        }
+    }
 }
 
-/// Return early with an error if a condition is not satisfied.
+/// Attach context to a fallible value, with the original names of this crate.
 ///
-/// If the provided condition is not met, this macro will trigger a [`bail!`] with the given
-/// error message. It is a convenient way to validate inputs.
+/// Implemented for everything that implements [`Context`], which it is built on. A message
+/// given to `wrap` is kept as is instead of being formatted, so a string literal is not
+/// allocated.
 ///
 /// # Example
 /// ```
 /// # use fu::*;
-/// fn check(value: i32) -> Result<()> {
-///     ensure!(value >= 0, "value must be non-negative");
-///     Ok(())
+/// fn example() -> Result<std::fs::File> {
+///     std::fs::File::open("does-not-exist").wrap("failed to open config")
 /// }
-/// assert!(check(-1).is_err());
+/// assert!(example().is_err());
+///
+/// let map = std::collections::HashMap::<&str, i32>::new();
+/// let result = map.get("key").wrap("missing key");
+/// assert!(result.is_err());
 /// ```
-#[macro_export]
-macro_rules! ensure {
-    ($condition:expr, $($arg:tt)*) => {
-        if !($condition) {
-            $crate::bail!($($arg)*);
-        }
-    };
+pub trait Wrap<T>: Context<T> {
+    /// Wrap the error value with a context message.
+    #[cfg_attr(not(fu_no_location), track_caller)]
+    fn wrap<C: Into<Cow<'static, str>>>(self, context: C) -> Result<T> {
+        self.wrap_at(|| Some(context.into()), Location::caller())
+    }
+
+    /// Wrap the error value with a context message that is only evaluated if an error occurred.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// let path = "does-not-exist";
+    /// let result = std::fs::File::open(path).wrap_with(|| format!("failed to open {}", path));
+    /// assert!(result.is_err());
+    /// ```
+    #[cfg_attr(not(fu_no_location), track_caller)]
+    fn wrap_with<C, F>(self, f: F) -> Result<T>
+    where
+        C: Into<Cow<'static, str>>,
+        F: FnOnce() -> C,
+    {
+        self.wrap_at(|| Some(f().into()), Location::caller())
+    }
+
+    /// Wrap the error value with the caller's location only, without a context message.
+    ///
+    /// The error renders as the first message of the chain of the original error, at the
+    /// location of the caller.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// fu::set_color(false);
+    /// let err = "x".parse::<u8>().loc().unwrap_err();
+    /// assert_eq!(err.message(), None);
+    /// # #[cfg(not(fu_no_location))]
+    /// assert_eq!(err.location().line(), line!() - 3);
+    /// # #[cfg(not(fu_no_location))]
+    /// assert!(err.to_string().starts_with("invalid digit found in string    "));
+    /// ```
+    #[cfg_attr(not(fu_no_location), track_caller)]
+    fn loc(self) -> Result<T> {
+        self.wrap_at(|| None, Location::caller())
+    }
 }
 
+impl<T, C: Context<T>> Wrap<T> for C {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -198,4 +2691,785 @@ mod tests {
         assert!(example_function(50).is_ok());
         assert!(example_function(101).is_err());
     }
+
+    #[cfg(not(fu_no_location))]
+    #[test]
+    fn test_wrap_location() {
+        let file = std::fs::File::open("does-not-exist");
+        let line = line!() + 1;
+        let err = file.wrap("open").unwrap_err();
+        assert_eq!(err.inner.frames[0].location.file(), file!());
+        assert_eq!(err.inner.frames[0].location.line(), line);
+        assert!(err.source().is_some());
+    }
+
+    #[cfg(not(fu_no_location))]
+    #[test]
+    fn test_wrap_with_is_lazy() {
+        let ok: std::result::Result<i32, std::io::Error> = Ok(1);
+        let value = ok.wrap_with(|| -> String { panic!("context evaluated on success") });
+        assert_eq!(value.unwrap(), 1);
+
+        let line = line!() + 1;
+        let err = "x".parse::<i32>().wrap_with(|| "parse").unwrap_err();
+        assert_eq!(err.inner.frames[0].location.line(), line);
+        assert!(err.to_string().contains("parse"));
+    }
+
+    #[cfg(not(fu_no_location))]
+    #[test]
+    fn test_wrap_option() {
+        assert_eq!(Some(1).wrap("missing").unwrap(), 1);
+
+        let line = line!() + 1;
+        let err = None::<i32>.wrap("missing").unwrap_err();
+        assert_eq!(err.inner.frames[0].location.line(), line);
+        assert!(err.source().is_none());
+        assert!(err.to_string().contains("missing"));
+    }
+
+    #[test]
+    fn test_downcast() {
+        let mut err = "x".parse::<i32>().wrap("parse").unwrap_err();
+        assert!(err.is::<std::num::ParseIntError>());
+        assert!(!err.is::<std::io::Error>());
+        assert!(err.downcast_mut::<std::num::ParseIntError>().is_some());
+
+        let err = err.downcast::<std::io::Error>().unwrap_err();
+        assert!(err.source().is_some());
+        assert!(err.downcast::<std::num::ParseIntError>().is_ok());
+        assert!(error!("no source").downcast::<std::io::Error>().is_err());
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn test_provide() {
+        #[derive(Debug)]
+        struct Retry(u32);
+
+        #[derive(Debug)]
+        struct Throttled;
+
+        impl core::fmt::Display for Throttled {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                write!(f, "throttled")
+            }
+        }
+
+        impl StdError for Throttled {
+            fn provide<'a>(&'a self, request: &mut core::error::Request<'a>) {
+                request.provide_value(Retry(3));
+            }
+        }
+
+        let line = line!() + 1;
+        let err = error!(Throttled).context("request failed");
+        let location = core::error::request_ref::<Location>(&err).unwrap();
+        assert_eq!(location.line(), line);
+        assert_eq!(core::error::request_value::<Retry>(&err).unwrap().0, 3);
+        assert_eq!(
+            core::error::request_value::<Severity>(&err),
+            Some(Severity::Error)
+        );
+        assert!(core::error::request_value::<ErrorKind>(&err).is_none());
+    }
+
+    #[cfg(feature = "backtrace")]
+    #[test]
+    fn test_backtrace_status() {
+        use std::backtrace::BacktraceStatus;
+
+        let err = error!("oops");
+        match err.backtrace().status() {
+            BacktraceStatus::Captured => assert!(err.to_string().contains("Stack backtrace:")),
+            _ => assert!(!err.to_string().contains("Stack backtrace:")),
+        }
+    }
+
+    #[test]
+    fn test_context_stack() {
+        fn inner() -> Result<()> {
+            "x".parse::<i32>().wrap("inner")?;
+            Ok(())
+        }
+        let err = inner().wrap("middle").unwrap_err().context("outer");
+        let messages: Vec<_> = err
+            .inner
+            .frames
+            .iter()
+            .map(|f| f.message.as_deref())
+            .collect();
+        assert_eq!(messages, [Some("inner"), Some("middle"), Some("outer")]);
+        assert!(err.is::<std::num::ParseIntError>());
+
+        let rendered = err.to_string();
+        let outer = rendered.find("outer").unwrap();
+        let middle = rendered.find("middle").unwrap();
+        let inner = rendered.find("inner").unwrap();
+        assert!(outer < middle && middle < inner);
+    }
+
+    #[test]
+    fn test_error_from_value() {
+        let io = std::io::Error::other("disk on fire");
+        let err = error!(io);
+        assert!(err.is::<std::io::Error>());
+        assert!(err.inner.frames[0].message.is_none());
+        assert!(err.to_string().contains("disk on fire"));
+
+        let inner = error!("inner");
+        let err = error!(inner);
+        assert_eq!(err.inner.frames.len(), 2);
+        assert!(err.source().is_none());
+
+        let name = "fu";
+        assert!(error!("inline {name}").to_string().contains("inline fu"));
+    }
+
+    #[test]
+    fn test_ensure_without_message() {
+        fn check(x: i32) -> Result<()> {
+            ensure!(x > 0);
+            Ok(())
+        }
+        assert!(check(1).is_ok());
+        let err = check(0).unwrap_err();
+        assert_eq!(
+            err.inner.frames[0].message.as_deref(),
+            Some("Condition failed: `x > 0`")
+        );
+    }
+
+    #[cfg(not(fu_no_location))]
+    #[test]
+    fn test_ensure_grammar() {
+        fn check(x: i32, message: &String) -> Result<()> {
+            ensure!(x > 0,);
+            ensure!(x > 1, "x is {}", x,);
+            ensure!(x > 2, message);
+            ensure!(x > 3, error!("too small").with_code("E1"),);
+            Ok(())
+        }
+        let message = String::from("precomputed");
+        assert!(check(4, &message).is_ok());
+        assert_eq!(check(1, &message).unwrap_err().message(), Some("x is 1"));
+        let err = check(2, &message).unwrap_err();
+        assert_eq!(err.message(), Some("precomputed"));
+        assert_eq!(err.location().file(), file!());
+        let err = check(3, &message).unwrap_err();
+        assert_eq!(err.inner.frames.len(), 1);
+        assert_eq!(err.code(), Some("E1"));
+    }
+
+    #[test]
+    fn test_payloads() {
+        #[derive(Debug, PartialEq)]
+        struct Request(u32);
+
+        let inner = error!("inner").with_payload(Request(1)).with_payload(7u8);
+        let err = error!("outer")
+            .with_payload(Request(2))
+            .chain(inner.clone());
+        assert_eq!(err.get::<Request>(), Some(&Request(2)));
+        assert_eq!(err.get::<u8>(), Some(&7));
+        assert_eq!(inner.get::<Request>(), Some(&Request(1)));
+        assert_eq!(err.clone().context("more").get::<u8>(), Some(&7));
+        assert_eq!(err.get::<u16>(), None);
+    }
+
+    #[test]
+    fn test_bail_if() {
+        fn check(x: i32) -> Result<()> {
+            bail_if!(x == 0);
+            bail_if!(x < 0, "{} is negative", x,);
+            bail_if!(x > 9, error!("too big").with_code("E3"));
+            Ok(())
+        }
+        assert!(check(5).is_ok());
+        assert_eq!(
+            check(0).unwrap_err().message(),
+            Some("Condition failed: `!(x == 0)`")
+        );
+        assert_eq!(check(-1).unwrap_err().message(), Some("-1 is negative"));
+        assert_eq!(check(10).unwrap_err().code(), Some("E3"));
+    }
+
+    #[cfg(not(fu_no_location))]
+    #[test]
+    fn test_ensure_some() {
+        let line = line!() + 2;
+        fn parse(input: &str) -> Result<(i32, i32)> {
+            let (a, b) = ensure_some!(input.split_once(','), code = "E2", "no comma in {}", input);
+            let a = ensure_some!(a.parse().ok());
+            Ok((a, b.parse().wrap("parse")?))
+        }
+        assert_eq!(parse("1,2").unwrap(), (1, 2));
+
+        let err = parse("1").unwrap_err();
+        assert_eq!(err.message(), Some("no comma in 1"));
+        assert_eq!(err.code(), Some("E2"));
+        assert_eq!(err.location().line(), line);
+
+        let err = parse("x,2").unwrap_err();
+        assert_eq!(
+            err.message(),
+            Some("Condition failed: `a.parse().ok()` is `None`")
+        );
+    }
+
+    #[test]
+    fn test_ensure_eq_ne() {
+        fn check(a: i32, b: i32) -> Result<()> {
+            ensure_eq!(a + 1, b, "off by {}", b - a);
+            ensure_ne!(a, 0);
+            Ok(())
+        }
+        assert!(check(1, 2).is_ok());
+
+        let err = check(1, 5).unwrap_err();
+        assert_eq!(
+            err.inner.frames[0].message.as_deref(),
+            Some("off by 4 (left: 2, right: 5)")
+        );
+
+        let err = check(0, 1).unwrap_err();
+        assert_eq!(
+            err.inner.frames[0].message.as_deref(),
+            Some("Condition failed: `a != 0` (left: 0, right: 0)")
+        );
+    }
+
+    #[test]
+    fn test_ensure_matches() {
+        fn check(value: Option<i32>) -> Result<()> {
+            ensure_matches!(value, Some(n) if *n > 0);
+            ensure_matches!(value, Some(1..=9), "{} digits", "too many");
+            Ok(())
+        }
+        assert!(check(Some(5)).is_ok());
+
+        let err = check(None).unwrap_err();
+        assert_eq!(
+            err.inner.frames[0].message.as_deref(),
+            Some("Condition failed: `value` does not match `Some(n) if *n > 0` (value: None)")
+        );
+
+        let err = check(Some(10)).unwrap_err();
+        assert_eq!(
+            err.inner.frames[0].message.as_deref(),
+            Some("too many digits (value: Some(10))")
+        );
+    }
+
+    #[test]
+    fn test_secrets_survive_wrapping() {
+        let inner: Result<()> = Err(error!("denied").with_secret("token", concat!("ab", "c")));
+        let err = inner
+            .wrap("login failed")
+            .unwrap_err()
+            .with_secret("token", "def");
+        let fields: Vec<_> = err.fields().collect();
+        assert_eq!(fields, [("token", "[REDACTED]"), ("token", "[REDACTED]")]);
+        let secrets: Vec<_> = err.secrets().collect();
+        assert_eq!(secrets, [("token", "abc"), ("token", "def")]);
+        assert_eq!(err.secret("token"), Some("def"));
+        assert_eq!(err.secret("password"), None);
+        assert!(!format!("{:#}", err).contains("abc"));
+    }
+
+    #[test]
+    fn test_fields_survive_wrapping() {
+        fn inner() -> Result<()> {
+            Err(error!("inner").with("path", "/etc/app.toml"))
+        }
+        let err = inner().wrap("outer").unwrap_err().with("attempt", 2);
+        let fields: Vec<_> = err.fields().collect();
+        assert_eq!(fields, [("path", "/etc/app.toml"), ("attempt", "2")]);
+        assert!(err
+            .to_string()
+            .contains("\n  path = /etc/app.toml\n  attempt = 2"));
+    }
+
+    #[test]
+    fn test_exit_code_survives_wrapping() {
+        let inner: Result<()> = Err(error!("usage").with_exit_code(2));
+        let err = inner.wrap("outer").unwrap_err();
+        assert_eq!(err.exit_code(), Some(2));
+        assert_eq!(err.with_exit_code(3).exit_code(), Some(3));
+        assert_eq!(error!().exit_code(), None);
+    }
+
+    #[test]
+    fn test_status_survives_wrapping() {
+        let inner: Result<()> = Err(error!("not found").with_status(404));
+        let err = inner.wrap("failed to load user").unwrap_err();
+        assert_eq!(err.status(), Some(404));
+        assert_eq!(err.with_status(410).status(), Some(410));
+        assert_eq!(error!().status(), None);
+    }
+
+    #[cfg(not(fu_no_location))]
+    #[test]
+    fn test_macro_fields() {
+        fn check(id: u32) -> Result<()> {
+            ensure!(id > 0, "invalid id"; id = id);
+            bail!(code = "E1", "user {} not found", id; id = id, retry = false,)
+        }
+        let err = check(0).unwrap_err();
+        assert_eq!(err.message(), Some("invalid id"));
+        assert_eq!(err.fields().collect::<Vec<_>>(), [("id", "0")]);
+        let err = check(7).unwrap_err();
+        assert_eq!(err.message(), Some("user 7 not found"));
+        assert_eq!(err.code(), Some("E1"));
+        assert_eq!(
+            err.fields().collect::<Vec<_>>(),
+            [("id", "7"), ("retry", "false")]
+        );
+        assert_eq!(err.location().line(), line!() - 12);
+    }
+
+    #[test]
+    fn test_literal_message() {
+        let err = error!("literal");
+        assert!(matches!(err.head().message, Some(Cow::Borrowed("literal"))));
+        let err = error!("escaped {{}}");
+        assert!(matches!(
+            err.head().message,
+            Some(Cow::Borrowed("escaped {}"))
+        ));
+
+        let value = 42;
+        let err = error!("formatted {value}");
+        assert!(matches!(err.head().message, Some(Cow::Owned(_))));
+        assert_eq!(err.message(), Some("formatted 42"));
+    }
+
+    #[cfg(not(fu_no_location))]
+    #[test]
+    fn test_define_error() {
+        define_error! {
+            enum StoreError {
+                Missing { key: String, shard: u8 } => "key {key} not found in shard {shard}",
+                Poisoned => "store is poisoned",
+                Io { path: &'static str, source: std::io::Error } => ("failed to read {}", path),
+            }
+        }
+
+        let err = StoreError::Missing {
+            key: "a".into(),
+            shard: 2,
+        };
+        assert_eq!(err.to_string(), "key a not found in shard 2");
+        assert!(StdError::source(&err).is_none());
+        assert_eq!(StoreError::Poisoned.to_string(), "store is poisoned");
+
+        let io = StoreError::Io {
+            path: "db",
+            source: std::io::Error::other("eof"),
+        };
+        assert_eq!(io.to_string(), "failed to read db");
+        assert_eq!(StdError::source(&io).unwrap().to_string(), "eof");
+
+        let line = line!() + 1;
+        let err = Error::from(io);
+        assert!(err.is::<StoreError>());
+        assert_eq!(err.location().line(), line);
+    }
+
+    #[cfg(not(fu_no_location))]
+    #[test]
+    fn test_loc() {
+        set_color(false);
+        let line = line!() + 1;
+        let err = Err::<(), _>(error!("inner")).loc().unwrap_err();
+        assert_eq!(err.message(), None);
+        assert_eq!(err.location().line(), line);
+        assert_eq!(err.frames().len(), 2);
+        let rendered = err.to_string();
+        assert!(rendered.starts_with(&format!("inner    {}:[{}:", file!(), line)));
+        assert!(!rendered.contains("Caused by:"));
+
+        let err = None::<u8>.loc().unwrap_err();
+        let location = format!("{}:[{}:30]", file!(), line!() - 1);
+        assert!(err.to_string().starts_with(&location));
+    }
+
+    #[cfg(not(fu_no_location))]
+    #[test]
+    fn test_context_trait() {
+        let line = line!() + 1;
+        let err = "x".parse::<u8>().context("invalid port").unwrap_err();
+        assert_eq!(err.message(), Some("invalid port"));
+        assert!(err.is::<core::num::ParseIntError>());
+        assert_eq!(err.location().line(), line);
+
+        let err = Err::<(), _>(error!("inner"))
+            .with_context(|| 42)
+            .unwrap_err();
+        assert_eq!(err.message(), Some("42"));
+        assert_eq!(err.frames().len(), 2);
+        assert_eq!(
+            None::<u8>.context("empty").unwrap_err().message(),
+            Some("empty")
+        );
+
+        let err = Err::<(), _>(error!("inner")).wrap("literal").unwrap_err();
+        assert!(matches!(err.head().message, Some(Cow::Borrowed("literal"))));
+    }
+
+    #[cfg(not(fu_no_location))]
+    #[test]
+    fn test_lazy() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let err = Error::lazy(move || {
+            counter.fetch_add(1, Ordering::Relaxed);
+            "formatted".into()
+        })
+        .context("outer");
+        assert!(err.to_string().contains("Caused by: formatted"));
+        assert_eq!(err.frames().last().unwrap().message(), Some("formatted"));
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+
+        let name = String::from("bob");
+        let err = error!(lazy "no user {name} in {}", "db");
+        assert_eq!(err.message(), Some("no user bob in db"));
+        assert_eq!(err.location().line(), line!() - 2);
+    }
+
+    #[test]
+    fn test_transient() {
+        assert!(!error!("oops").is_transient());
+        assert!(error!("oops").transient().context("outer").is_transient());
+        assert!(!error!("oops")
+            .transient()
+            .context("outer")
+            .permanent()
+            .is_transient());
+
+        let refused = std::io::Error::from(std::io::ErrorKind::ConnectionRefused);
+        assert!(!Error::from(refused).is_transient());
+        let busy = error!("busy").transient();
+        let err = error!("outer").chain(std::fmt::Error).chain(busy);
+        assert!(err.is_transient());
+    }
+
+    #[cfg(not(fu_no_location))]
+    #[test]
+    fn test_code() {
+        fn check() -> Result<()> {
+            ensure!(false, code = "E1", "bad");
+            Ok(())
+        }
+        let line = line!() - 3;
+        let err = check().wrap("outer").unwrap_err();
+        assert_eq!(err.code(), Some("E1"));
+        assert_eq!(err.inner.frames[0].location.line(), line);
+        assert!(err.to_string().starts_with("[E1] outer"));
+    }
+
+    #[test]
+    fn test_help() {
+        let inner: Result<()> = Err(error!("exists").help("use --force"));
+        let err = inner
+            .wrap("copy failed")
+            .unwrap_err()
+            .help("or pick another path");
+        assert_eq!(
+            err.helps().collect::<Vec<_>>(),
+            ["use --force", "or pick another path"]
+        );
+        assert!(err
+            .to_string()
+            .contains("\n  help: use --force\n  help: or pick another path"));
+    }
+
+    #[test]
+    fn test_notes() {
+        let err = error!("exists")
+            .note("while copying a.txt")
+            .context("copy failed")
+            .note("in dry-run mode")
+            .help("use --force");
+        assert_eq!(
+            err.notes().collect::<Vec<_>>(),
+            ["while copying a.txt", "in dry-run mode"]
+        );
+        assert!(err.to_string().contains(
+            "\n  note: while copying a.txt\n  note: in dry-run mode\n  help: use --force"
+        ));
+    }
+
+    #[test]
+    fn test_see() {
+        let err = error!("inner").see("https://example.com/inner");
+        assert_eq!(
+            err.clone().context("outer").url(),
+            Some("https://example.com/inner")
+        );
+
+        let err = err
+            .context("outer")
+            .see("https://example.com/outer")
+            .help("retry");
+        assert_eq!(err.url(), Some("https://example.com/outer"));
+        assert!(err
+            .to_string()
+            .contains("\n  help: retry\n  see: https://example.com/outer"));
+    }
+
+    #[test]
+    fn test_causes_cycle() {
+        #[derive(Debug)]
+        struct Cyclic;
+
+        impl core::fmt::Display for Cyclic {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                write!(f, "cyclic")
+            }
+        }
+
+        impl StdError for Cyclic {
+            fn source(&self) -> Option<&(dyn StdError + 'static)> {
+                Some(self)
+            }
+        }
+
+        set_color(false);
+        let err = error!("outer").chain(Cyclic);
+        let mut causes = err.causes();
+        assert_eq!(causes.by_ref().count(), 1);
+        assert!(causes.is_truncated());
+        assert!(err
+            .to_string()
+            .contains("Caused by: cyclic\n  (further causes omitted)"));
+
+        let err = error!("outer").chain(std::io::Error::other("inner"));
+        let mut causes = err.causes();
+        assert_eq!(causes.by_ref().count(), 1);
+        assert!(!causes.is_truncated());
+
+        // A newtype has the address and size of its source, but is another error.
+        #[derive(Debug)]
+        struct Wrapper(std::io::Error);
+
+        impl core::fmt::Display for Wrapper {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                write!(f, "wrapper")
+            }
+        }
+
+        impl StdError for Wrapper {
+            fn source(&self) -> Option<&(dyn StdError + 'static)> {
+                Some(&self.0)
+            }
+        }
+
+        let err = error!("outer").chain(Wrapper(std::io::Error::other("inner")));
+        let mut causes = err.causes();
+        assert_eq!(causes.by_ref().count(), 2);
+        assert!(!causes.is_truncated());
+    }
+
+    #[test]
+    fn test_boxed_round_trip() {
+        set_color(false);
+        let err = error!("inner").context("outer");
+        let boxed: Box<dyn StdError + Send + Sync> = err.clone().into();
+        assert_eq!(boxed.to_string(), err.to_string());
+        assert_eq!(format!("{:?}", boxed), format!("{:?}", err));
+
+        let back = Error::from_boxed(boxed);
+        assert_eq!(back.frames().len(), 2);
+        assert_eq!(back.location(), err.location());
+    }
+
+    #[test]
+    fn test_io_round_trip() {
+        let err = error!("inner").context("outer").with("key", 1);
+        let io = std::io::Error::from(err.clone());
+        assert_eq!(io.kind(), std::io::ErrorKind::Other);
+        assert!(io.to_string().starts_with("outer"));
+
+        let back = Error::from(io);
+        assert_eq!(back.frames().len(), 2);
+        assert_eq!(back.location(), err.location());
+        assert_eq!(back.fields().collect::<Vec<_>>(), [("key", "1")]);
+    }
+
+    #[test]
+    fn test_clone_shares_source() {
+        let err = "x".parse::<i32>().wrap("parse").unwrap_err().with("n", 1);
+        let clone = err.clone();
+        assert_eq!(err.to_string(), clone.to_string());
+        assert!(clone.is::<std::num::ParseIntError>());
+
+        // The source can only be taken out once it is no longer shared.
+        let err = err.downcast::<std::num::ParseIntError>().unwrap_err();
+        drop(clone);
+        assert!(err.downcast::<std::num::ParseIntError>().is_ok());
+    }
+
+    #[test]
+    fn test_literal_message_is_borrowed() {
+        let err = error!("static");
+        assert!(matches!(
+            err.inner.frames[0].message,
+            Some(Cow::Borrowed("static"))
+        ));
+
+        let n = 1;
+        let err = error!("formatted {n}");
+        assert!(matches!(&err.inner.frames[0].message, Some(Cow::Owned(m)) if m == "formatted 1"));
+
+        let err = err.context("static context");
+        assert!(matches!(
+            err.inner.frames[1].message,
+            Some(Cow::Borrowed(_))
+        ));
+    }
+
+    #[cfg(not(fu_no_location))]
+    #[test]
+    fn test_function_name() {
+        fn outer() -> Error {
+            error!("oops")
+        }
+        let closure = || error!("oops");
+        assert_eq!(
+            outer().location().function(),
+            Some("fu::tests::test_function_name::outer")
+        );
+        assert_eq!(
+            closure().location().function(),
+            Some("fu::tests::test_function_name")
+        );
+        assert_eq!(Location::caller().function(), None);
+        assert!(outer()
+            .to_string()
+            .contains(" in fu::tests::test_function_name::outer"));
+    }
+
+    #[cfg(not(fu_no_location))]
+    #[test]
+    fn test_question_mark_location() {
+        fn parse() -> Result<i32> {
+            Ok("abc".parse::<i32>()?)
+        }
+        let err = parse().unwrap_err();
+        assert_eq!(err.inner.frames[0].location.file(), file!());
+    }
+
+    #[cfg(not(fu_no_location))]
+    #[test]
+    fn test_question_mark_display() {
+        fn read() -> Result<()> {
+            Err(std::io::Error::other("disk on fire"))?
+        }
+        let err = read().unwrap_err();
+        let rendered = err.themed(Theme::PLAIN).to_string();
+        let headline = format!("disk on fire    {}", err.location());
+        assert_eq!(rendered.lines().next(), Some(headline.as_str()));
+        assert!(!rendered.contains("Caused by:"));
+
+        let err = read().context("failed to load").unwrap_err();
+        let rendered = err.themed(Theme::PLAIN).to_string();
+        assert!(rendered.starts_with("failed to load    "));
+        assert!(rendered.contains("\n  Caused by: disk on fire"));
+    }
+
+    #[test]
+    #[should_panic(expected = "assertion failed: `Ok::<_, Error>(1)` is an error\n  got: Ok(1)")]
+    fn test_assert_err_ok() {
+        assert_err!(Ok::<_, Error>(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "contains \"other\"\n  error: inner")]
+    fn test_assert_err_contains_mismatch() {
+        // Split the needle so that it is not found in a source snippet of this line.
+        assert_err_contains!(Err::<(), _>(error!("inner")), concat!("oth", "er"));
+    }
+
+    #[test]
+    #[should_panic(expected = "matches `|_| false`")]
+    fn test_assert_err_predicate_mismatch() {
+        assert_err!(Err::<(), _>(error!("inner")), |_| false);
+    }
+
+    #[test]
+    fn test_chain_eq() {
+        let a = "x".parse::<i32>().wrap("parse").unwrap_err();
+        let b = "y".parse::<i32>().wrap("parse").unwrap_err();
+        assert!(a.chain_eq(&b));
+
+        let c = "".parse::<i32>().wrap("parse").unwrap_err();
+        assert!(!a.chain_eq(&c));
+        assert!(!a.chain_eq(&a.clone().context("outer")));
+    }
+
+    #[cfg(not(fu_no_location))]
+    #[test]
+    fn test_from_string() {
+        fn check() -> Result<()> {
+            Err(format!("invalid {}", "name"))?;
+            Ok(())
+        }
+
+        let err = check().unwrap_err();
+        assert_eq!(err.message(), Some("invalid name"));
+        assert_eq!(err.location().line(), line!() - 6);
+        assert!(!err.has_source());
+    }
+
+    #[cfg(not(fu_no_location))]
+    #[test]
+    fn test_from_boxed() {
+        let boxed: Box<dyn StdError + Send + Sync> = Box::new(std::fmt::Error);
+        let err = Error::from_boxed(boxed);
+        assert_eq!(err.location().line(), line!() - 1);
+        assert!(err.is::<std::fmt::Error>());
+
+        let boxed: Box<dyn StdError + Send + Sync> = Box::new(error!("inner").context("outer"));
+        let err = Error::from_boxed(boxed);
+        assert_eq!(err.inner.frames.len(), 2);
+        assert!(!err.has_source());
+    }
+
+    #[test]
+    fn test_chain_nests_sources() {
+        let err = error!("outer")
+            .chain(std::io::Error::other("first"))
+            .chain(std::fmt::Error)
+            .chain(std::io::Error::other("third"));
+        let causes: Vec<_> = err.causes().map(|e| e.to_string()).collect();
+        assert_eq!(causes, ["first", &std::fmt::Error.to_string(), "third"]);
+
+        // Leaf sources lead on to the ones nested under them for `source` too.
+        let sources = std::iter::successors(err.source(), |&e| e.source()).count();
+        assert_eq!(sources, 3);
+
+        assert!(err.is::<std::io::Error>());
+        let shared = err.clone();
+        let mut err = err.downcast::<std::io::Error>().unwrap_err();
+        assert_eq!(err.causes().count(), 3);
+        drop(shared);
+
+        assert!(err.downcast_mut::<std::io::Error>().is_some());
+        let io = err.downcast::<std::io::Error>().unwrap();
+        assert_eq!(io.to_string(), "first");
+    }
+
+    #[test]
+    fn test_chain_error_after_source() {
+        let err = error!("outer")
+            .chain(std::fmt::Error)
+            .chain(error!("inner"));
+        assert_eq!(err.inner.frames.len(), 1);
+        let causes: Vec<_> = err.causes().collect();
+        assert_eq!(causes.len(), 2);
+        assert!(causes[1].is::<Error>());
+    }
 }
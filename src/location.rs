@@ -0,0 +1,203 @@
+//! The [`Location`] an error was created at.
+
+/// The file, line and column an error was created at.
+///
+/// Building with `RUSTFLAGS="--cfg fu_no_location"` compiles locations out: the macros no
+/// longer record `file!()`, `line!()` and `column!()`, `Location` is zero-sized and every
+/// location is [unknown](Location::is_known), so that no source paths end up in the binary.
+/// Renderers then omit locations.
+///
+/// # Example
+/// ```
+/// # use fu::Location;
+/// let location = Location::new("src/main.rs", 10, 5);
+/// # #[cfg(not(fu_no_location))]
+/// assert_eq!(location.file(), "src/main.rs");
+/// # #[cfg(not(fu_no_location))]
+/// assert_eq!(location.to_string(), "src/main.rs:[10:5]");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Location {
+    #[cfg(not(fu_no_location))]
+    file: &'static str,
+    #[cfg(not(fu_no_location))]
+    line: u32,
+    #[cfg(not(fu_no_location))]
+    column: u32,
+    #[cfg(not(fu_no_location))]
+    function: Option<&'static str>,
+}
+
+impl Location {
+    /// A location that was not recorded, with an empty file and a zero line and column.
+    pub const UNKNOWN: Location = Location::new("", 0, 0);
+
+    /// Creates a new `Location`.
+    #[cfg(not(fu_no_location))]
+    pub const fn new(file: &'static str, line: u32, column: u32) -> Self {
+        Location {
+            file,
+            line,
+            column,
+            function: None,
+        }
+    }
+
+    /// Creates a new `Location`.
+    #[cfg(fu_no_location)]
+    pub const fn new(_file: &'static str, _line: u32, _column: u32) -> Self {
+        Location {}
+    }
+
+    /// Sets the path of the function enclosing this location.
+    ///
+    /// The macros record it automatically, it is rendered next to the file location.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// fn load_config() -> Error {
+    ///     error!("oops")
+    /// }
+    /// # #[cfg(not(fu_no_location))]
+    /// let function = load_config().location().function().unwrap();
+    /// # #[cfg(not(fu_no_location))]
+    /// assert!(function.ends_with("::load_config"));
+    /// ```
+    #[cfg_attr(fu_no_location, allow(unused_mut, unused_variables))]
+    pub const fn with_function(mut self, function: &'static str) -> Self {
+        #[cfg(not(fu_no_location))]
+        {
+            self.function = Some(function);
+        }
+        self
+    }
+
+    /// Returns the location of the caller of the enclosing `#[track_caller]` function.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::Location;
+    /// let location = Location::caller();
+    /// # #[cfg(not(fu_no_location))]
+    /// assert_eq!(location.line(), line!() - 2);
+    /// ```
+    #[cfg_attr(not(fu_no_location), track_caller)]
+    pub fn caller() -> Self {
+        #[cfg(not(fu_no_location))]
+        return core::panic::Location::caller().into();
+        #[cfg(fu_no_location)]
+        Location::UNKNOWN
+    }
+
+    /// Returns false if the location was not recorded, see [`Location::UNKNOWN`].
+    pub const fn is_known(&self) -> bool {
+        !self.file().is_empty()
+    }
+
+    /// Returns the name of the source file.
+    pub const fn file(&self) -> &'static str {
+        #[cfg(not(fu_no_location))]
+        return self.file;
+        #[cfg(fu_no_location)]
+        ""
+    }
+
+    /// Returns the line number.
+    pub const fn line(&self) -> u32 {
+        #[cfg(not(fu_no_location))]
+        return self.line;
+        #[cfg(fu_no_location)]
+        0
+    }
+
+    /// Returns the column number.
+    pub const fn column(&self) -> u32 {
+        #[cfg(not(fu_no_location))]
+        return self.column;
+        #[cfg(fu_no_location)]
+        0
+    }
+
+    /// Returns the path of the enclosing function, if it was recorded.
+    pub const fn function(&self) -> Option<&'static str> {
+        #[cfg(not(fu_no_location))]
+        return self.function;
+        #[cfg(fu_no_location)]
+        None
+    }
+}
+
+/// Displays the file, line and column, the function is not included. Unknown locations are
+/// displayed as `<unknown>`.
+impl core::fmt::Display for Location {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if !self.is_known() {
+            return f.write_str("<unknown>");
+        }
+        write!(f, "{}:[{}:{}]", self.file(), self.line(), self.column())
+    }
+}
+
+impl From<&'static core::panic::Location<'static>> for Location {
+    fn from(location: &'static core::panic::Location<'static>) -> Self {
+        Location::new(location.file(), location.line(), location.column())
+    }
+}
+
+impl From<(&'static str, u32, u32)> for Location {
+    fn from((file, line, column): (&'static str, u32, u32)) -> Self {
+        Location::new(file, line, column)
+    }
+}
+
+/// Expands to the path of the enclosing function, without trailing closure segments.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __function {
+    () => {{
+        fn f() {}
+        $crate::__private::function_name(::core::any::type_name_of_val(&f))
+    }};
+}
+
+/// Expands to the [`Location`] of the macro call, with the enclosing function.
+#[cfg(not(fu_no_location))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __location {
+    () => {
+        $crate::Location::new(file!(), line!(), column!()).with_function($crate::__function!())
+    };
+}
+
+/// Expands to an unknown [`Location`], locations are compiled out.
+#[cfg(fu_no_location)]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __location {
+    () => {
+        $crate::Location::UNKNOWN
+    };
+}
+
+/// Expands to a function marked `#[track_caller]`, for the conversions generated by macros.
+#[cfg(not(fu_no_location))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __track_caller {
+    ($($item:tt)*) => {
+        #[track_caller]
+        $($item)*
+    };
+}
+
+/// Expands to the function unchanged, locations are compiled out.
+#[cfg(fu_no_location)]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __track_caller {
+    ($($item:tt)*) => {
+        $($item)*
+    };
+}
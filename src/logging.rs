@@ -0,0 +1,140 @@
+//! [`log`] support: logging errors without giving up ownership of them.
+
+use crate::{Error, Result};
+
+/// Logs the full chain of `err` at `level`, reporting the location of the error. Errors are
+/// only reported when the level is enabled.
+pub(crate) fn log(err: &Error, level: log::Level) {
+    if level > log::max_level() {
+        return;
+    }
+    let metadata = log::Metadata::builder().level(level).target("fu").build();
+    let logger = log::logger();
+    if !logger.enabled(&metadata) {
+        return;
+    }
+    #[cfg(feature = "std")]
+    err.reported();
+    let location = err.location();
+    logger.log(
+        &log::Record::builder()
+            .args(format_args!("{:#}", err))
+            .metadata(metadata)
+            .file(location.is_known().then(|| location.file()))
+            .line(location.is_known().then(|| location.line()))
+            .module_path(location.function())
+            .build(),
+    );
+}
+
+/// Logs the error of a [`Result`] while passing the result through, or falling back to a value.
+pub trait ResultExt<T>: Sized {
+    /// Logs the error, if any, at the [`Error`](log::Level::Error) level, see [`Error::log`].
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// fn load() -> Result<String> {
+    ///     std::fs::read_to_string("config.toml")
+    ///         .wrap("failed to read config")
+    ///         .log_err()
+    /// }
+    /// ```
+    fn log_err(self) -> Self {
+        self.log_err_at(log::Level::Error)
+    }
+
+    /// Logs the error, if any, at the given `level`, see [`Error::log`].
+    fn log_err_at(self, level: log::Level) -> Self;
+
+    /// Returns the value, or logs the error at the [`Error`](log::Level::Error) level and
+    /// returns the default value.
+    ///
+    /// # Example
+    /// ```
+    /// # use fu::*;
+    /// let retries: u32 = std::env::var("RETRIES")
+    ///     .wrap("RETRIES is not set")
+    ///     .and_then(|s| s.parse().wrap("RETRIES is not a number"))
+    ///     .or_default_logged();
+    /// ```
+    fn or_default_logged(self) -> T
+    where
+        T: Default,
+    {
+        self.or_else_logged(|_| T::default())
+    }
+
+    /// Returns the value, or logs the error at the [`Error`](log::Level::Error) level and
+    /// computes a fallback from it with `f`.
+    fn or_else_logged<F: FnOnce(Error) -> T>(self, f: F) -> T;
+}
+
+impl<T> ResultExt<T> for Result<T> {
+    fn log_err_at(self, level: log::Level) -> Self {
+        self.map_err(|err| err.log(level))
+    }
+
+    fn or_else_logged<F: FnOnce(Error) -> T>(self, f: F) -> T {
+        self.unwrap_or_else(|err| f(err.log(log::Level::Error)))
+    }
+}
+
+#[cfg(all(test, not(fu_no_location)))]
+mod tests {
+    use std::sync::Mutex;
+
+    use crate::*;
+
+    static RECORDS: Mutex<Vec<(log::Level, String, u32)>> = Mutex::new(Vec::new());
+
+    struct TestLogger;
+
+    impl log::Log for TestLogger {
+        fn enabled(&self, _: &log::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record<'_>) {
+            let entry = (
+                record.level(),
+                record.args().to_string(),
+                record.line().unwrap(),
+            );
+            RECORDS.lock().unwrap().push(entry);
+        }
+
+        fn flush(&self) {}
+    }
+
+    #[test]
+    fn test_log_err() {
+        log::set_logger(&TestLogger).unwrap();
+        log::set_max_level(log::LevelFilter::Info);
+        set_color(false);
+
+        let result: Result<()> = Err(error!("oops"));
+        let line = line!() - 1;
+        let err = result.log_err_at(log::Level::Warn).unwrap_err();
+        assert_eq!(err.message(), Some("oops"));
+
+        let _ = err.clone().log(log::Level::Debug);
+        let _ = err.log(log::Level::Error);
+
+        let value: u32 = Err(error!("inner").context("fallback")).or_default_logged();
+        assert_eq!(value, 0);
+        let value = Err(error!("other")).or_else_logged(|err| err.frames().len());
+        assert_eq!(value, 1);
+        assert_eq!(Ok(7).or_else_logged(|_| 0), 7);
+
+        let records = RECORDS.lock().unwrap();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0].0, log::Level::Warn);
+        assert!(records[0].1.starts_with("oops    "));
+        assert_eq!(records[0].2, line);
+        assert_eq!(records[1].0, log::Level::Error);
+        assert_eq!(records[2].0, log::Level::Error);
+        assert!(records[2].1.contains("Caused by: inner"));
+        assert!(records[3].1.starts_with("other    "));
+    }
+}
@@ -0,0 +1,174 @@
+//! Rendering of [`Error`] reports as Markdown, see [`Error::markdown`].
+
+use alloc::string::{String, ToString};
+use core::fmt::{Display, Formatter, Result, Write};
+
+use crate::render::PlainLocation;
+use crate::{Error, Location, Severity};
+
+/// Renders an [`Error`] as Markdown, see [`Error::markdown`].
+#[derive(Debug)]
+pub struct Markdown<'a>(pub(crate) &'a Error);
+
+/// Writes text with the Markdown special characters escaped, indenting continuation lines by
+/// `indent` to keep them in the enclosing list item.
+struct Escaped<'a> {
+    text: &'a str,
+    indent: usize,
+}
+
+impl Display for Escaped<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        for c in self.text.chars() {
+            match c {
+                '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '~' | '|' => {
+                    write!(f, "\\{}", c)?
+                }
+                '\n' => write!(f, "\n{:indent$}", "", indent = self.indent)?,
+                c => write!(f, "{}", c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn escaped(text: &str, indent: usize) -> Escaped<'_> {
+    Escaped { text, indent }
+}
+
+/// Writes the location of a frame as inline code after `separator`, if it is known.
+fn write_location(f: &mut Formatter<'_>, separator: &str, location: &Location) -> Result {
+    if !location.is_known() {
+        return Ok(());
+    }
+    let location = PlainLocation(location).to_string();
+    // A code span containing backticks needs a longer delimiter.
+    if location.contains('`') {
+        write!(f, "{}`` {} ``", separator, location)
+    } else {
+        write!(f, "{}`{}`", separator, location)
+    }
+}
+
+impl Display for Markdown<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let err = self.0;
+        let mut headline = String::new();
+        if err.severity() != Severity::Error {
+            write!(headline, "{}: ", err.severity())?;
+        }
+        if let Some(code) = err.code() {
+            write!(headline, "\\[{}\\] ", escaped(code, 0))?;
+        }
+        // An error without a message of its own, like a `?` conversion, is headed by the first
+        // message of its chain.
+        let mut links = crate::render::links(err).into_iter();
+        if let Some(link) = links.next() {
+            write!(headline, "{}", escaped(&link.message, 0))?;
+        }
+        let headline = headline.trim_end();
+        let separator = if headline.is_empty() {
+            ""
+        } else {
+            write!(f, "**{}**", headline)?;
+            " "
+        };
+        write_location(f, separator, &err.location())?;
+
+        for (depth, link) in links.enumerate() {
+            if depth == 0 {
+                f.write_str("\n")?;
+            }
+            let indent = depth * 2;
+            write!(
+                f,
+                "\n{:indent$}- {}",
+                "",
+                escaped(&link.message, indent + 2)
+            )?;
+            if let Some(location) = &link.location {
+                write_location(f, " ", location)?;
+            }
+        }
+
+        // A different bullet starts a new list, instead of continuing the causes.
+        let mut sections = false;
+        let mut section = |f: &mut Formatter<'_>| {
+            if !sections {
+                sections = true;
+                f.write_str("\n")?;
+            }
+            f.write_str("\n* ")
+        };
+        for (key, value) in err.fields() {
+            section(f)?;
+            write!(f, "{} = `{}`", escaped(key, 2), value.replace('`', "'"))?;
+        }
+        for note in err.notes() {
+            section(f)?;
+            write!(f, "**note:** {}", escaped(note, 2))?;
+        }
+        for help in err.helps() {
+            section(f)?;
+            write!(f, "**help:** {}", escaped(help, 2))?;
+        }
+        if let Some(url) = err.url() {
+            section(f)?;
+            write!(f, "**see:** <{}>", url)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, not(fu_no_location)))]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_markdown() {
+        let err = Error::new(Some("inner *x*"), Location::new("src/main.rs", 3, 7))
+            .chain(std::io::Error::other("io\nerror"))
+            .context("outer")
+            .with_code("E1")
+            .with("path", "app.toml")
+            .help("try again")
+            .see("https://example.com");
+        let markdown = err.markdown().to_string();
+
+        assert!(markdown.starts_with("**\\[E1\\] outer** `"));
+        assert!(markdown.contains(
+            "\n\n- inner \\*x\\* `src/main.rs:[3:7]`\n  - io\n    error\n\n* path = `app.toml`\n"
+        ));
+        assert!(markdown.ends_with("\n* **help:** try again\n* **see:** <https://example.com>"));
+
+        let err = error!("oops").with_severity(Severity::Warning);
+        assert!(err
+            .markdown()
+            .to_string()
+            .starts_with("**warning: oops** `"));
+        assert!(!err.markdown().to_string().contains('\n'));
+    }
+
+    #[test]
+    fn test_markdown_chain() {
+        fn read() -> Result<()> {
+            Err(std::io::Error::other("disk on fire"))?;
+            Ok(())
+        }
+
+        let markdown = read().unwrap_err().markdown().to_string();
+        assert!(markdown.starts_with("**disk on fire** `src/markdown.rs:["));
+        assert!(!markdown.contains('\n'));
+
+        let markdown = Error::new(None::<&str>, Location::new("src/main.rs", 3, 7))
+            .markdown()
+            .to_string();
+        assert_eq!(markdown, "`src/main.rs:[3:7]`");
+
+        let err = error!("outer").chain(error!("inner").context("middle"));
+        let markdown = err.markdown().to_string();
+        assert!(markdown.contains("\n\n- middle `src/markdown.rs:["));
+        assert!(markdown.contains("\n  - inner `src/markdown.rs:["));
+        assert!(!markdown.contains("Caused by:"));
+    }
+}
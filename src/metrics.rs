@@ -0,0 +1,124 @@
+//! [`metrics`] support: counters of created and reported errors.
+//!
+//! Two counters are incremented through the installed [`metrics`] recorder:
+//!
+//! - `fu_errors_created_total` when an [`Error`] is created, see
+//!   [`set_error_hook`](crate::set_error_hook) for what counts as created,
+//! - `fu_errors_reported_total` when an error is printed by [`Report`](crate::Report) or
+//!   [`exit`](crate::exit), or logged with `Error::log` at an enabled level.
+//!
+//! Both are labeled with the `code` and the `kind` of the error, empty if it has none, and the
+//! `file` of its [location](Error::location).
+
+use alloc::string::{String, ToString};
+
+use crate::Error;
+
+/// Increments the counter of created errors.
+pub(crate) fn record_created(err: &Error) {
+    metrics::counter!("fu_errors_created_total", &labels(err)).increment(1);
+}
+
+/// Increments the counter of reported errors.
+pub(crate) fn record_reported(err: &Error) {
+    metrics::counter!("fu_errors_reported_total", &labels(err)).increment(1);
+}
+
+fn labels(err: &Error) -> [(&'static str, String); 3] {
+    [
+        ("code", err.code().unwrap_or_default().to_string()),
+        (
+            "kind",
+            err.kind().map(|kind| kind.to_string()).unwrap_or_default(),
+        ),
+        ("file", err.location().file().to_string()),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use ::metrics::{Counter, CounterFn, Gauge, Histogram, Key, KeyName, Metadata, Recorder};
+    use ::metrics::{SharedString, Unit};
+
+    use crate::*;
+
+    /// Records the key of every counter incremented.
+    #[derive(Default)]
+    struct TestRecorder(Arc<Mutex<Vec<String>>>);
+
+    struct TestCounter(String, Arc<Mutex<Vec<String>>>);
+
+    impl CounterFn for TestCounter {
+        fn increment(&self, _: u64) {
+            self.1.lock().unwrap().push(self.0.clone());
+        }
+
+        fn absolute(&self, _: u64) {}
+    }
+
+    impl Recorder for TestRecorder {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+            let labels: Vec<_> = key
+                .labels()
+                .map(|l| format!("{}={}", l.key(), l.value()))
+                .collect();
+            let key = format!("{}{{{}}}", key.name(), labels.join(","));
+            Counter::from_arc(Arc::new(TestCounter(key, self.0.clone())))
+        }
+
+        fn register_gauge(&self, _: &Key, _: &Metadata<'_>) -> Gauge {
+            Gauge::noop()
+        }
+
+        fn register_histogram(&self, _: &Key, _: &Metadata<'_>) -> Histogram {
+            Histogram::noop()
+        }
+    }
+
+    #[cfg(not(fu_no_location))]
+    #[test]
+    fn test_metrics() {
+        let recorder = TestRecorder::default();
+        ::metrics::with_local_recorder(&recorder, || {
+            let missing = std::io::Error::from(std::io::ErrorKind::NotFound);
+            let err = Error::from(missing).with_code("E404");
+            super::record_reported(&err);
+        });
+        // Errors created by other tests, e.g. by the error hook, are counted as well.
+        let file = file!();
+        let counters = recorder.0.lock().unwrap();
+        let counters: Vec<_> = counters.iter().filter(|c| c.contains(file)).collect();
+        assert_eq!(
+            counters,
+            [
+                &format!(
+                    "fu_errors_created_total{{code=,kind=not found,file={}}}",
+                    file
+                ),
+                &format!(
+                    "fu_errors_reported_total{{code=E404,kind=not found,file={}}}",
+                    file
+                ),
+            ]
+        );
+    }
+
+    #[cfg(feature = "log")]
+    #[test]
+    fn test_filtered_log_not_reported() {
+        let recorder = TestRecorder::default();
+        ::metrics::with_local_recorder(&recorder, || {
+            let _ = error!("too verbose").log(log::Level::Trace);
+        });
+        let counters = recorder.0.lock().unwrap();
+        assert!(!counters
+            .iter()
+            .any(|c| c.starts_with("fu_errors_reported_total")));
+    }
+}
@@ -0,0 +1,65 @@
+//! [`miette`] support: [`Error`] as a [`Diagnostic`].
+
+use alloc::boxed::Box;
+use core::fmt::Display;
+
+use miette::Diagnostic;
+
+use crate::Error;
+
+/// Maps the [code](Error::with_code), [help messages](Error::help) and [URL](Error::see) of the
+/// error onto the diagnostic. The locations are part of the message, rendered with `Display`.
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// use miette::Diagnostic;
+///
+/// let err = error!("destination exists").with_code("E17").help("try running with --force");
+/// assert_eq!(Diagnostic::code(&err).unwrap().to_string(), "E17");
+/// assert_eq!(Diagnostic::help(&err).unwrap().to_string(), "try running with --force");
+/// ```
+impl Diagnostic for Error {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        self.code().map(|code| Box::new(code) as Box<dyn Display>)
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        if self.inner.help.is_empty() {
+            return None;
+        }
+        Some(Box::new(self.inner.help.join("\n")))
+    }
+
+    fn url<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        self.url().map(|url| Box::new(url) as Box<dyn Display>)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::miette::Diagnostic;
+
+    use crate::*;
+
+    #[test]
+    fn test_diagnostic() {
+        let err = error!("oops");
+        assert!(Diagnostic::code(&err).is_none());
+        assert!(Diagnostic::help(&err).is_none());
+        assert!(Diagnostic::url(&err).is_none());
+
+        let err = err.help("first").help("second");
+        let help = Diagnostic::help(&err).unwrap().to_string();
+        assert_eq!(help, "first\nsecond");
+
+        let err = err.see("https://example.com");
+        assert_eq!(
+            Diagnostic::url(&err).unwrap().to_string(),
+            "https://example.com"
+        );
+
+        let report = ::miette::Report::new(err.with_code("E1"));
+        assert_eq!(report.code().unwrap().to_string(), "E1");
+    }
+}
@@ -0,0 +1,218 @@
+//! Rendering panics the same way as errors, and catching them as errors.
+
+use std::cell::Cell;
+use std::panic::{PanicHookInfo, UnwindSafe};
+use std::sync::{Mutex, Once};
+
+use crate::{render, Error, Location, Result};
+
+thread_local! {
+    /// Whether the current thread is running a closure given to [`catch`].
+    static CATCHING: Cell<bool> = const { Cell::new(false) };
+    /// The location of the last panic caught by [`catch`] on the current thread.
+    static CAUGHT_AT: Cell<Option<Location>> = const { Cell::new(None) };
+}
+
+/// Installs a panic hook that prints panics in the same format as errors.
+///
+/// The message is followed by the location of the panic, and by a backtrace if the
+/// `RUST_BACKTRACE` environment variable enables it, see [`std::backtrace::Backtrace::capture`].
+///
+/// # Example
+/// ```should_panic
+/// fu::install_panic_hook();
+/// panic!("oops"); // thread 'main' panicked: oops    src/main.rs:[2:1]
+/// ```
+pub fn install_panic_hook() {
+    std::panic::set_hook(Box::new(|info| {
+        if record_caught(info) {
+            return;
+        }
+        let thread = std::thread::current();
+        let report = PanicReport {
+            thread: thread.name(),
+            message: payload_message(info.payload()),
+            location: info.location().map(|l| (l.file(), l.line(), l.column())),
+            backtrace: std::backtrace::Backtrace::capture(),
+        };
+        eprintln!("{}", report);
+    }));
+}
+
+/// Runs `f`, turning a panic into an [`Error`] instead of unwinding further.
+///
+/// The error is created at the location of the panic if it is known, or at the caller
+/// otherwise, and its source is a [`Panic`] with the message of the panic. The first call
+/// installs a panic hook that keeps caught panics from being printed and forwards the others
+/// to the previous hook; installing another hook afterwards prevents that.
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// let err = fu::catch(|| -> u32 { panic!("plugin crashed") }).unwrap_err();
+/// assert!(err.is::<Panic>());
+/// assert_eq!(err.downcast_ref::<Panic>().unwrap().message(), "plugin crashed");
+/// # #[cfg(not(fu_no_location))]
+/// assert_eq!(err.location().line(), line!() - 4);
+///
+/// assert_eq!(fu::catch(|| 42).unwrap(), 42);
+/// ```
+#[cfg_attr(not(fu_no_location), track_caller)]
+pub fn catch<T, F>(f: F) -> Result<T>
+where
+    F: FnOnce() -> T + UnwindSafe,
+{
+    static HOOK: Once = Once::new();
+    HOOK.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            if !record_caught(info) {
+                previous(info);
+            }
+        }));
+    });
+
+    let caller = Location::caller();
+    let catching = CATCHING.replace(true);
+    let result = std::panic::catch_unwind(f);
+    CATCHING.set(catching);
+    result.map_err(|payload| {
+        let location = CAUGHT_AT.take().unwrap_or(caller);
+        let message = payload_message(&*payload).to_string();
+        Error::new(Some("panicked"), location).chain(Panic { message })
+    })
+}
+
+/// Records the location of a panic raised inside [`catch`], returning false for other panics.
+fn record_caught(info: &PanicHookInfo<'_>) -> bool {
+    if !CATCHING.get() {
+        return false;
+    }
+    let location = info
+        .location()
+        .map(|l| Location::new(intern(l.file()), l.line(), l.column()));
+    CAUGHT_AT.set(location);
+    true
+}
+
+/// Returns a `'static` copy of `file`, leaking it once per distinct file.
+///
+/// Panic locations come from `file!()` and are `'static` in practice, but the hook only hands
+/// out borrowed ones. There are as many distinct files as source files in the program.
+fn intern(file: &str) -> &'static str {
+    static FILES: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+    let mut files = FILES.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(interned) = files.iter().find(|interned| **interned == file) {
+        return interned;
+    }
+    let interned: &'static str = Box::leak(file.into());
+    files.push(interned);
+    interned
+}
+
+/// The source of an [`Error`] created from a panic by [`catch`].
+#[derive(Debug, Clone)]
+pub struct Panic {
+    message: String,
+}
+
+impl Panic {
+    /// Returns the message of the panic.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl core::fmt::Display for Panic {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for Panic {}
+
+/// Returns the message of a panic payload, which is a `&str` or a `String` for the `panic!`
+/// macro.
+pub(crate) fn payload_message(payload: &(dyn std::any::Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&'static str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "Box<dyn Any>"
+    }
+}
+
+struct PanicReport<'a> {
+    thread: Option<&'a str>,
+    message: &'a str,
+    location: Option<(&'a str, u32, u32)>,
+    backtrace: std::backtrace::Backtrace,
+}
+
+impl core::fmt::Display for PanicReport<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let thread = self.thread.unwrap_or("<unnamed>");
+        write!(f, "thread '{}' panicked: {}", thread, self.message)?;
+        if let Some((file, line, column)) = self.location {
+            write!(f, "    ")?;
+            render::write_file_location(
+                f,
+                file,
+                line,
+                column,
+                None,
+                render::active_theme().as_ref(),
+            )?;
+        }
+        if self.backtrace.status() == std::backtrace::BacktraceStatus::Captured {
+            write!(f, "\n\nStack backtrace:\n{}", self.backtrace)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_panic_report() {
+        crate::set_color(false);
+        let report = PanicReport {
+            thread: Some("main"),
+            message: "oops",
+            location: Some(("src/main.rs", 2, 5)),
+            backtrace: std::backtrace::Backtrace::disabled(),
+        };
+        assert_eq!(
+            report.to_string(),
+            "thread 'main' panicked: oops    src/main.rs:[2:5]"
+        );
+
+        let payload: Box<dyn std::any::Any + Send> = Box::new(String::from("owned"));
+        assert_eq!(payload_message(&*payload), "owned");
+        assert_eq!(payload_message(&42), "Box<dyn Any>");
+    }
+
+    #[cfg(not(fu_no_location))]
+    #[test]
+    fn test_catch() {
+        let err = catch(|| {
+            let _ = catch(|| panic!("inner"));
+            panic!("outer")
+        })
+        .unwrap_err();
+        assert_eq!(err.message(), Some("panicked"));
+        assert_eq!(err.downcast_ref::<Panic>().unwrap().message(), "outer");
+        assert_eq!(err.location().file(), file!());
+        assert_eq!(err.location().line(), line!() - 6);
+
+        let payload = std::panic::AssertUnwindSafe(|| std::panic::panic_any(7));
+        let err = catch(payload).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<Panic>().unwrap().message(),
+            "Box<dyn Any>"
+        );
+    }
+}
@@ -0,0 +1,45 @@
+//! [`pyo3`] support: [`Error`] as a Python exception.
+
+use pyo3::exceptions::PyValueError;
+use pyo3::PyErr;
+
+use crate::{Error, Theme};
+
+/// Converts the error into a Python `ValueError` whose message is the rendered error, with its
+/// location and chain, without colors.
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// use pyo3::PyResult;
+///
+/// fn parse_port(input: &str) -> PyResult<u16> {
+///     let port = input.parse().wrap("invalid port")?;
+///     Ok(port)
+/// }
+/// ```
+impl From<Error> for PyErr {
+    fn from(err: Error) -> Self {
+        PyValueError::new_err(err.themed(Theme::PLAIN).to_string())
+    }
+}
+
+#[cfg(all(test, not(fu_no_location)))]
+mod tests {
+    use ::pyo3::exceptions::PyValueError;
+    use ::pyo3::{PyErr, Python};
+
+    use crate::*;
+
+    #[test]
+    fn test_py_err() {
+        let err = PyErr::from(error!("no such user").context("failed to get user"));
+        Python::initialize();
+        Python::attach(|py| {
+            assert!(err.is_instance_of::<PyValueError>(py));
+            let message = err.value(py).to_string();
+            assert!(message.starts_with("failed to get user    "));
+            assert!(message.contains("Caused by: no such user"));
+        });
+    }
+}
@@ -0,0 +1,1770 @@
+//! Rendering of [`Error`] reports and the global settings that control it.
+
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+#[cfg(any(feature = "html", feature = "markdown"))]
+use alloc::vec::Vec;
+use core::error::Error as StdError;
+use core::fmt::Write;
+use core::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+
+use crate::{Causes, Error, ErrorGroup, Location, Severity};
+
+const UNSET: u8 = 0;
+const OFF: u8 = 1;
+const ON: u8 = 2;
+
+static COLOR: AtomicU8 = AtomicU8::new(UNSET);
+
+/// Enables or disables colored output globally.
+///
+/// This overrides the automatic detection, see [`detect_color`].
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// fu::set_color(false);
+/// assert!(!error!("oops").to_string().contains('\x1b'));
+/// ```
+pub fn set_color(enabled: bool) {
+    COLOR.store(if enabled { ON } else { OFF }, Ordering::Relaxed);
+}
+
+/// Returns whether errors are rendered with colors.
+///
+/// Unless overridden with [`set_color`], this is the result of [`detect_color`], computed once.
+/// Without the `std` feature, colors are disabled by default.
+pub fn color_enabled() -> bool {
+    match COLOR.load(Ordering::Relaxed) {
+        #[cfg(not(feature = "std"))]
+        UNSET => false,
+        #[cfg(feature = "std")]
+        UNSET => {
+            let enabled = detect_color();
+            // Only cache the detection if no one called `set_color` in the meantime.
+            let value = if enabled { ON } else { OFF };
+            let _ = COLOR.compare_exchange(UNSET, value, Ordering::Relaxed, Ordering::Relaxed);
+            enabled
+        }
+        value => value == ON,
+    }
+}
+
+/// Detects whether the environment supports colored output.
+///
+/// Colors are supported when stderr, where errors are usually reported, is a terminal and the
+/// [`NO_COLOR`](https://no-color.org) environment variable is not set or empty. The `FU_STYLE`
+/// environment variable takes precedence: `plain` disables colors, `color` enables them, and
+/// `json` disables them and renders errors as JSON, see [`render_default`].
+///
+/// # Example
+/// ```
+/// // Go back to automatic detection after overriding it.
+/// fu::set_color(false);
+/// fu::set_color(fu::detect_color());
+/// ```
+#[cfg(feature = "std")]
+pub fn detect_color() -> bool {
+    use std::io::IsTerminal;
+
+    match env_style() {
+        Some(EnvStyle::Plain | EnvStyle::Json) => return false,
+        Some(EnvStyle::Color) => return true,
+        None => {}
+    }
+    std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()) && std::io::stderr().is_terminal()
+}
+
+/// A report style selected by end users with the `FU_STYLE` environment variable.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EnvStyle {
+    Plain,
+    Color,
+    Json,
+}
+
+#[cfg(feature = "std")]
+fn parse_style(value: &str) -> Option<EnvStyle> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "plain" => Some(EnvStyle::Plain),
+        "color" | "colour" => Some(EnvStyle::Color),
+        "json" => Some(EnvStyle::Json),
+        _ => None,
+    }
+}
+
+/// Returns the style selected with `FU_STYLE`, read once.
+#[cfg(feature = "std")]
+fn env_style() -> Option<EnvStyle> {
+    static STYLE: std::sync::OnceLock<Option<EnvStyle>> = std::sync::OnceLock::new();
+    *STYLE.get_or_init(|| parse_style(&std::env::var("FU_STYLE").ok()?))
+}
+
+/// The styles used to render errors when colors are enabled.
+///
+/// Every style is an ANSI escape sequence written before the styled element, which is followed
+/// by a reset when the style is not empty.
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// let theme = Theme {
+///     message: "\x1b[1;31m",
+///     ..Theme::DEFAULT
+/// };
+/// let err = error!("oops");
+/// assert!(err.themed(theme).to_string().starts_with("\x1b[1;31moops\x1b[0m"));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    /// The style of the messages.
+    pub message: &'static str,
+    /// The style of the locations.
+    pub location: &'static str,
+    /// The style of the `Caused by:` labels.
+    pub caused_by: &'static str,
+    /// The style of the `warning:` prefix of warnings, see [`Severity`].
+    pub warning: &'static str,
+    /// The style of the `fatal:` prefix of fatal errors.
+    pub fatal: &'static str,
+}
+
+impl Theme {
+    /// The default theme, rendering locations in grey, and the prefixes of warnings in yellow and
+    /// of fatal errors in red.
+    pub const DEFAULT: Theme = Theme {
+        message: "",
+        location: "\x1b[90m",
+        caused_by: "",
+        warning: "\x1b[1;33m",
+        fatal: "\x1b[1;31m",
+    };
+
+    /// A theme without any styles.
+    pub const PLAIN: Theme = Theme {
+        message: "",
+        location: "",
+        caused_by: "",
+        warning: "",
+        fatal: "",
+    };
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::DEFAULT
+    }
+}
+
+#[cfg(feature = "std")]
+static THEME: std::sync::RwLock<Theme> = std::sync::RwLock::new(Theme::DEFAULT);
+
+/// Sets the theme used to render errors globally when colors are enabled.
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// fu::set_color(true);
+/// fu::set_theme(Theme {
+///     location: "\x1b[2;36m",
+///     ..Theme::DEFAULT
+/// });
+/// # #[cfg(not(fu_no_location))]
+/// assert!(error!("oops").to_string().contains("\x1b[2;36m"));
+/// ```
+#[cfg(feature = "std")]
+pub fn set_theme(theme: Theme) {
+    *THEME.write().unwrap_or_else(|e| e.into_inner()) = theme;
+}
+
+/// Returns the theme used to render errors when colors are enabled.
+pub fn theme() -> Theme {
+    #[cfg(feature = "std")]
+    return *THEME.read().unwrap_or_else(|e| e.into_inner());
+    #[cfg(not(feature = "std"))]
+    Theme::DEFAULT
+}
+
+/// Returns the theme to render with, or `None` if colors are disabled.
+pub(crate) fn active_theme() -> Option<Theme> {
+    color_enabled().then(theme)
+}
+
+/// Writes `value` in the given `style`.
+fn write_styled(
+    f: &mut core::fmt::Formatter<'_>,
+    style: &str,
+    value: impl core::fmt::Display,
+) -> core::fmt::Result {
+    if style.is_empty() {
+        write!(f, "{}", value)
+    } else {
+        write!(f, "{}{}\x1b[0m", style, value)
+    }
+}
+
+const WIDTH_UNSET: usize = usize::MAX;
+const WIDTH_OFF: usize = 0;
+
+static WIDTH: AtomicUsize = AtomicUsize::new(WIDTH_UNSET);
+
+/// Sets the width at which long messages are wrapped globally, or disables wrapping with `None`.
+///
+/// This overrides the automatic detection, see [`detect_width`]. Continuation lines are
+/// indented under the start of the message, and a location that does not fit after its message
+/// goes on its own line.
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// fu::set_color(false);
+/// fu::set_wrap_width(Some(50));
+/// let err = error!("the quick brown fox jumps over the lazy dog, twice and then once more");
+/// assert!(err.to_string().starts_with("the quick brown fox jumps over the lazy dog, twice\nand then once more"));
+/// # fu::set_wrap_width(None);
+/// ```
+pub fn set_wrap_width(width: Option<usize>) {
+    WIDTH.store(width.unwrap_or(WIDTH_OFF), Ordering::Relaxed);
+}
+
+/// Returns the width at which long messages are wrapped, if any.
+///
+/// Unless overridden with [`set_wrap_width`], this is the result of [`detect_width`], computed
+/// once. Without the `std` feature, messages are not wrapped by default.
+pub fn wrap_width() -> Option<usize> {
+    match WIDTH.load(Ordering::Relaxed) {
+        #[cfg(not(feature = "std"))]
+        WIDTH_UNSET => None,
+        #[cfg(feature = "std")]
+        WIDTH_UNSET => {
+            let width = detect_width();
+            // Only cache the detection if no one called `set_wrap_width` in the meantime.
+            let value = width.unwrap_or(WIDTH_OFF);
+            let _ =
+                WIDTH.compare_exchange(WIDTH_UNSET, value, Ordering::Relaxed, Ordering::Relaxed);
+            width
+        }
+        WIDTH_OFF => None,
+        width => Some(width),
+    }
+}
+
+/// Detects the width of the terminal errors are reported to.
+///
+/// The width is read from the `COLUMNS` environment variable when stderr is a terminal. Shells
+/// set it, but do not always export it; with the `terminal` feature, the size of the terminal
+/// is queried instead. There is no width otherwise.
+#[cfg(feature = "std")]
+pub fn detect_width() -> Option<usize> {
+    use std::io::IsTerminal;
+
+    let stderr = std::io::stderr();
+    if !stderr.is_terminal() {
+        return None;
+    }
+    let columns = match std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+    {
+        Some(columns) => columns,
+        None => terminal_width(&stderr)?,
+    };
+    (columns != WIDTH_OFF && columns != WIDTH_UNSET).then_some(columns)
+}
+
+/// Queries the width of the terminal behind stderr.
+#[cfg(feature = "terminal")]
+fn terminal_width(stderr: &std::io::Stderr) -> Option<usize> {
+    let (terminal_size::Width(width), _) = terminal_size::terminal_size_of(stderr)?;
+    Some(width.into())
+}
+
+#[cfg(all(feature = "std", not(feature = "terminal")))]
+fn terminal_width(_: &std::io::Stderr) -> Option<usize> {
+    None
+}
+
+/// How file paths are rendered in error locations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathStyle {
+    /// Render paths exactly as recorded by `file!()`.
+    Full,
+    /// Strip the Cargo registry or git checkout prefix from the paths of dependencies, and the
+    /// current directory from absolute paths. This is the default.
+    Short,
+}
+
+static PATH_STYLE: AtomicU8 = AtomicU8::new(UNSET);
+
+/// Sets how file paths are rendered in error locations globally.
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// fu::set_color(false);
+/// fu::set_path_style(PathStyle::Full);
+/// let err = Error::new(Some("oops"), Location::new("/home/me/.cargo/registry/src/index/dep-1.0.0/src/lib.rs", 1, 1));
+/// # #[cfg(not(fu_no_location))]
+/// assert!(err.to_string().contains("/home/me/.cargo/registry"));
+///
+/// fu::set_path_style(PathStyle::Short);
+/// # #[cfg(not(fu_no_location))]
+/// assert!(err.to_string().contains("    dep-1.0.0/src/lib.rs:[1:1]"));
+/// ```
+pub fn set_path_style(style: PathStyle) {
+    let value = match style {
+        PathStyle::Full => OFF,
+        PathStyle::Short => ON,
+    };
+    PATH_STYLE.store(value, Ordering::Relaxed);
+}
+
+/// Returns how file paths are rendered in error locations.
+pub fn path_style() -> PathStyle {
+    match PATH_STYLE.load(Ordering::Relaxed) {
+        OFF => PathStyle::Full,
+        _ => PathStyle::Short,
+    }
+}
+
+/// Shortens `file` according to [`PathStyle::Short`].
+fn short_path(file: &str) -> &str {
+    for marker in ["registry/src/", "registry\\src\\"] {
+        if let Some(index) = file.find(marker) {
+            // Skip the registry index directory, e.g. `index.crates.io-6f17d22bba15001f`.
+            let rest = &file[index + marker.len()..];
+            if let Some(separator) = rest.find(['/', '\\']) {
+                return &rest[separator + 1..];
+            }
+        }
+    }
+    for marker in ["git/checkouts/", "git\\checkouts\\"] {
+        if let Some(index) = file.find(marker) {
+            return &file[index + marker.len()..];
+        }
+    }
+    #[cfg(feature = "std")]
+    if let Some(dir) = current_dir() {
+        if let Some(rest) = file.strip_prefix(dir) {
+            if let Some(rest) = rest.strip_prefix(['/', '\\']) {
+                return rest;
+            }
+        }
+    }
+    file
+}
+
+#[cfg(feature = "std")]
+fn current_dir() -> Option<&'static str> {
+    static DIR: std::sync::OnceLock<Option<String>> = std::sync::OnceLock::new();
+    DIR.get_or_init(|| {
+        std::env::current_dir()
+            .ok()?
+            .into_os_string()
+            .into_string()
+            .ok()
+    })
+    .as_deref()
+}
+
+#[cfg(feature = "std")]
+static REMAPS: std::sync::RwLock<Vec<(String, String)>> = std::sync::RwLock::new(Vec::new());
+
+/// Replaces the `from` prefix of the file paths of locations with `to` when rendering them,
+/// like `--remap-path-prefix` does for the compiler.
+///
+/// Remaps apply to rendered and serialized locations, before the [path style](set_path_style).
+/// When several prefixes match a path, the last one added wins.
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// fu::set_color(false);
+/// fu::remap_path("/home/ci/build", "<src>");
+/// let err = Error::new(Some("oops"), Location::new("/home/ci/build/src/main.rs", 3, 7));
+/// # #[cfg(not(fu_no_location))]
+/// assert!(err.to_string().starts_with("oops    <src>/src/main.rs:[3:7]"));
+/// ```
+///
+/// [Hyperlinks](set_hyperlinks) open the remapped path, e.g. to find the files of a build from
+/// CI in a local checkout:
+/// ```
+/// # use fu::*;
+/// fu::set_color(true);
+/// fu::set_hyperlinks(Some(fu::FILE_LINK));
+/// fu::remap_path("/home/ci/build", "/home/me/app");
+/// let err = Error::new(Some("oops"), Location::new("/home/ci/build/src/main.rs", 3, 7));
+/// # #[cfg(not(fu_no_location))]
+/// assert!(err
+///     .to_string()
+///     .contains("\x1b]8;;file:///home/me/app/src/main.rs\x1b\\"));
+/// ```
+#[cfg(feature = "std")]
+pub fn remap_path(from: &str, to: &str) {
+    let mut remaps = REMAPS.write().unwrap_or_else(|e| e.into_inner());
+    remaps.push((from.into(), to.into()));
+}
+
+/// Returns `file` with its prefix remapped, see [`remap_path`].
+pub(crate) fn remapped(file: &str) -> Cow<'_, str> {
+    #[cfg(feature = "std")]
+    if let Some(path) = remap(&REMAPS.read().unwrap_or_else(|e| e.into_inner()), file) {
+        return Cow::Owned(path);
+    }
+    Cow::Borrowed(file)
+}
+
+#[cfg(feature = "std")]
+fn remap(remaps: &[(String, String)], file: &str) -> Option<String> {
+    remaps.iter().rev().find_map(|(from, to)| {
+        let rest = file.strip_prefix(from.as_str())?;
+        Some(format!("{}{}", to, rest))
+    })
+}
+
+/// Hyperlink template opening locations with the default handler for `file://` URLs.
+#[cfg(feature = "std")]
+pub const FILE_LINK: &str = "file://{path}";
+
+/// Hyperlink template opening locations in Visual Studio Code.
+#[cfg(feature = "std")]
+pub const VSCODE_LINK: &str = "vscode://file/{path}:{line}:{column}";
+
+#[cfg(feature = "std")]
+static HYPERLINK: std::sync::RwLock<Option<String>> = std::sync::RwLock::new(None);
+
+/// Turns locations into clickable [OSC-8] terminal hyperlinks, or disables them with `None`.
+///
+/// The URL is built from the `template`, where `{path}` is replaced by the absolute path of the
+/// file, and `{line}` and `{column}` by the position in it. See [`FILE_LINK`] and
+/// [`VSCODE_LINK`] for common templates. Hyperlinks are disabled by default and only emitted
+/// when [colors are enabled](color_enabled).
+///
+/// [OSC-8]: https://gist.github.com/egmontkob/eb114294efbcd5adb1944c9f3cb5feda
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// fu::set_color(true);
+/// fu::set_hyperlinks(Some(fu::VSCODE_LINK));
+/// let err = Error::new(Some("oops"), Location::new("/src/main.rs", 3, 7));
+/// # #[cfg(not(fu_no_location))]
+/// assert!(err.to_string().contains("\x1b]8;;vscode://file//src/main.rs:3:7\x1b\\"));
+/// ```
+#[cfg(feature = "std")]
+pub fn set_hyperlinks(template: Option<&str>) {
+    let mut hyperlink = HYPERLINK.write().unwrap_or_else(|e| e.into_inner());
+    *hyperlink = template.map(String::from);
+}
+
+/// Builds the hyperlink URL for a location, if hyperlinks are enabled.
+#[cfg(feature = "std")]
+fn hyperlink(file: &str, line: u32, column: u32) -> Option<String> {
+    let template = HYPERLINK.read().unwrap_or_else(|e| e.into_inner());
+    let template = template.as_deref()?;
+    let mut path = std::path::PathBuf::from(file);
+    if path.is_relative() {
+        path = std::env::current_dir().ok()?.join(path);
+    }
+    Some(
+        template
+            .replace("{path}", &path.to_string_lossy())
+            .replace("{line}", &line.to_string())
+            .replace("{column}", &column.to_string()),
+    )
+}
+
+#[cfg(feature = "std")]
+static LOCATION_FORMAT: std::sync::RwLock<Option<String>> = std::sync::RwLock::new(None);
+
+/// Sets how the file, line and column of locations are rendered, or restores the default
+/// `{file}:[{line}:{column}]` with `None`.
+///
+/// In the `format`, `{file}` is replaced by the path of the file, see [`set_path_style`], and
+/// `{line}` and `{column}` (or `{col}`) by the position in it. Editors and terminals usually
+/// recognize `{file}:{line}:{column}` as a clickable path.
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// fu::set_color(false);
+/// fu::set_location_format(Some("{file}:{line}:{column}"));
+/// let err = Error::new(Some("oops"), Location::new("src/main.rs", 10, 5));
+/// # #[cfg(not(fu_no_location))]
+/// assert!(err.to_string().starts_with("oops    src/main.rs:10:5"));
+/// # fu::set_location_format(None);
+/// ```
+#[cfg(feature = "std")]
+pub fn set_location_format(format: Option<&str>) {
+    let mut location_format = LOCATION_FORMAT.write().unwrap_or_else(|e| e.into_inner());
+    *location_format = format.map(String::from);
+}
+
+static COLUMNS: AtomicU8 = AtomicU8::new(UNSET);
+
+/// Shows or omits the column of locations globally, e.g. `src/main.rs:42` instead of
+/// `src/main.rs:[42:5]`.
+///
+/// Many log pipelines and editors only understand `file:line`. With a custom
+/// [location format](set_location_format), `{column}` is replaced by nothing, along with the `:`
+/// or `,` right before it. Columns are shown by default.
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// fu::set_color(false);
+/// fu::set_columns(false);
+/// let err = Error::new(Some("oops"), Location::new("src/main.rs", 42, 5));
+/// # #[cfg(not(fu_no_location))]
+/// assert!(err.to_string().starts_with("oops    src/main.rs:42"));
+/// ```
+pub fn set_columns(enabled: bool) {
+    COLUMNS.store(if enabled { ON } else { OFF }, Ordering::Relaxed);
+}
+
+/// Returns whether the column of locations is shown, see [`set_columns`].
+pub fn columns_enabled() -> bool {
+    COLUMNS.load(Ordering::Relaxed) != OFF
+}
+
+/// Writes the path, line and column of a location in the [location format](set_location_format).
+fn write_position(
+    f: &mut core::fmt::Formatter<'_>,
+    path: &str,
+    line: u32,
+    column: u32,
+) -> core::fmt::Result {
+    let column = columns_enabled().then_some(column);
+    #[cfg(feature = "std")]
+    if let Some(format) = LOCATION_FORMAT
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_deref()
+    {
+        return write_location_format(f, format, path, line, column);
+    }
+    match column {
+        Some(column) => write!(f, "{}:[{}:{}]", path, line, column),
+        None => write!(f, "{}:{}", path, line),
+    }
+}
+
+/// Writes a location in `format`, omitting the column and its separator if it is `None`.
+#[cfg(feature = "std")]
+fn write_location_format(
+    f: &mut core::fmt::Formatter<'_>,
+    format: &str,
+    path: &str,
+    line: u32,
+    column: Option<u32>,
+) -> core::fmt::Result {
+    const PLACEHOLDERS: [&str; 4] = ["{file}", "{line}", "{column}", "{col}"];
+    let mut rest = format;
+    while let Some(start) = rest.find('{') {
+        let placeholder = PLACEHOLDERS
+            .into_iter()
+            .find(|placeholder| rest[start..].starts_with(placeholder));
+        let mut text = &rest[..start];
+        if column.is_none() && matches!(placeholder, Some("{column}" | "{col}")) {
+            text = text.strip_suffix([':', ',']).unwrap_or(text);
+        }
+        f.write_str(text)?;
+        rest = &rest[start..];
+        match placeholder {
+            Some("{file}") => f.write_str(path)?,
+            Some("{line}") => write!(f, "{}", line)?,
+            Some(_) => {
+                if let Some(column) = column {
+                    write!(f, "{}", column)?;
+                }
+            }
+            None => {
+                f.write_str("{")?;
+                rest = &rest[1..];
+                continue;
+            }
+        }
+        rest = &rest[placeholder.map_or(0, str::len)..];
+    }
+    f.write_str(rest)
+}
+
+fn write_location(
+    f: &mut core::fmt::Formatter<'_>,
+    location: &Location,
+    theme: Option<&Theme>,
+) -> core::fmt::Result {
+    if !location.is_known() {
+        return Ok(());
+    }
+    write_file_location(
+        f,
+        location.file(),
+        location.line(),
+        location.column(),
+        location.function(),
+        theme,
+    )
+}
+
+pub(crate) fn write_file_location(
+    f: &mut core::fmt::Formatter<'_>,
+    file: &str,
+    line: u32,
+    column: u32,
+    function: Option<&str>,
+    theme: Option<&Theme>,
+) -> core::fmt::Result {
+    let remapped = remapped(file);
+    let path = match path_style() {
+        PathStyle::Full => &remapped,
+        PathStyle::Short => short_path(&remapped),
+    };
+    let style = theme.map_or("", |theme| theme.location);
+    f.write_str(style)?;
+    #[cfg(feature = "std")]
+    let link = hyperlink(&remapped, line, column).filter(|_| theme.is_some());
+    #[cfg(feature = "std")]
+    if let Some(link) = &link {
+        write!(f, "\x1b]8;;{}\x1b\\", link)?;
+    }
+    write_position(f, path, line, column)?;
+    #[cfg(feature = "std")]
+    if link.is_some() {
+        write!(f, "\x1b]8;;\x1b\\")?;
+    }
+    if let Some(function) = function {
+        write!(f, " in {}", function)?;
+    }
+    if !style.is_empty() {
+        write!(f, "\x1b[0m")?;
+    }
+    Ok(())
+}
+
+fn write_caused_by(f: &mut core::fmt::Formatter<'_>, theme: Option<&Theme>) -> core::fmt::Result {
+    write_styled(f, theme.map_or("", |theme| theme.caused_by), "Caused by:")?;
+    f.write_str(" ")
+}
+
+/// Returns true if `message` is the `previous` message rendered, or ends it after a `: `.
+///
+/// Many errors include the message of their source in their own, as in `open config: no such
+/// file`, which would otherwise be repeated by the next line of the chain.
+fn is_repeated(previous: Option<&str>, message: &str) -> bool {
+    previous.is_some_and(|previous| {
+        previous == message
+            || previous
+                .strip_suffix(message)
+                .is_some_and(|rest| rest.ends_with(": "))
+    })
+}
+
+/// The width of the `Caused by: ` label.
+const CAUSED_BY: usize = "Caused by: ".len();
+
+/// The fewest columns messages are wrapped at, however deep in the chain they are.
+const MIN_WRAP: usize = 40;
+
+/// Writes `text` in `style`, starting at `column`, wrapped at `width` if any.
+///
+/// Continuation lines are indented to `column`, so they stay under the start of the text. Words
+/// longer than a line are not broken. Returns the column the text ends at.
+fn write_wrapped(
+    f: &mut core::fmt::Formatter<'_>,
+    text: &str,
+    column: usize,
+    width: Option<usize>,
+    style: &str,
+) -> Result<usize, core::fmt::Error> {
+    let Some(width) = width else {
+        write_styled(f, style, text)?;
+        return Ok(column + text.chars().count());
+    };
+    let available = width.saturating_sub(column).max(MIN_WRAP);
+    let mut end = column;
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            write!(f, "\n{:column$}", "", column = column)?;
+        }
+        let mut current = String::new();
+        let mut length = 0;
+        for (j, word) in line.split(' ').enumerate() {
+            let word_length = word.chars().count();
+            if j > 0 {
+                if length > 0 && length + 1 + word_length > available {
+                    write_styled(f, style, &current)?;
+                    write!(f, "\n{:column$}", "", column = column)?;
+                    current.clear();
+                    current.push_str(word);
+                    length = word_length;
+                    continue;
+                }
+                current.push(' ');
+                length += 1;
+            }
+            current.push_str(word);
+            length += word_length;
+        }
+        if !current.is_empty() {
+            write_styled(f, style, &current)?;
+        }
+        end = column + length;
+    }
+    Ok(end)
+}
+
+/// Renders a location without styles, to measure it.
+pub(crate) struct PlainLocation<'a>(pub(crate) &'a Location);
+
+impl core::fmt::Display for PlainLocation<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write_location(f, self.0, None)
+    }
+}
+
+/// A message of the chain of an error with the location of its frame, see [`links`].
+#[cfg(any(feature = "html", feature = "markdown"))]
+pub(crate) struct Link<'a> {
+    pub(crate) message: Cow<'a, str>,
+    pub(crate) location: Option<Location>,
+}
+
+/// Returns the messages of the chain of `err`, outermost first, with the locations of their
+/// frames, for the renderers without styles. Nested errors are flattened into the chain instead
+/// of rendered with their `Display`.
+#[cfg(any(feature = "html", feature = "markdown"))]
+pub(crate) fn links(err: &Error) -> Vec<Link<'_>> {
+    let mut links = Vec::new();
+    push_links(err, &mut links);
+    links
+}
+
+#[cfg(any(feature = "html", feature = "markdown"))]
+fn push_links<'a>(err: &'a Error, links: &mut Vec<Link<'a>>) {
+    for frame in err.frames() {
+        if let Some(message) = crate::translate::message(frame) {
+            let location = Some(frame.location());
+            links.push(Link { message, location });
+        }
+    }
+    for cause in err.causes() {
+        match cause.downcast_ref::<Error>() {
+            Some(err) => push_links(err, links),
+            None => links.extend(crate::cause_message(cause).map(|message| Link {
+                message,
+                location: None,
+            })),
+        }
+    }
+}
+
+/// Writes the location after a message ending at `column`, or on its own line indented to
+/// `indent` if it does not fit in the wrap width. Unknown locations are omitted.
+fn write_location_after(
+    f: &mut core::fmt::Formatter<'_>,
+    location: &Location,
+    column: usize,
+    indent: usize,
+    options: &RenderOptions,
+) -> core::fmt::Result {
+    if !options.locations || !location.is_known() {
+        return Ok(());
+    }
+    let fits = options.width.is_none_or(|width| {
+        column + 4 + PlainLocation(location).to_string().chars().count() <= width
+    });
+    if fits {
+        f.write_str("    ")?;
+    } else {
+        write!(f, "\n{:indent$}", "", indent = indent)?;
+    }
+    write_location(f, location, options.theme.as_ref())
+}
+
+/// Writes to `out`, indenting every line after the first one.
+pub(crate) struct Indented<'a> {
+    pub(crate) out: &'a mut dyn core::fmt::Write,
+    pub(crate) indent: usize,
+}
+
+impl core::fmt::Write for Indented<'_> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let mut lines = s.split('\n');
+        if let Some(first) = lines.next() {
+            self.out.write_str(first)?;
+        }
+        for line in lines {
+            write!(self.out, "\n{:indent$}{}", "", line, indent = self.indent)?;
+        }
+        Ok(())
+    }
+}
+
+/// A cause of an error, as it is rendered.
+enum Cause<'a> {
+    /// A nested error, rendered with the same options instead of its own `Display`.
+    Error(&'a Error),
+    /// A group of errors, rendered with its header followed by its errors, with the same options.
+    Group(&'a ErrorGroup),
+    /// Any other error, rendered with its message.
+    Message(String),
+}
+
+impl<'a> Cause<'a> {
+    fn new(cause: &'a (dyn StdError + 'static)) -> Self {
+        if let Some(err) = cause.downcast_ref::<Error>() {
+            return Cause::Error(err);
+        }
+        match cause.downcast_ref::<ErrorGroup>() {
+            Some(group) => Cause::Group(group),
+            None => Cause::Message(cause.to_string()),
+        }
+    }
+
+    /// Returns true if the cause only repeats the `previous` message, see [`is_repeated`].
+    fn is_repeated(&self, previous: Option<&str>) -> bool {
+        matches!(self, Cause::Message(msg) if is_repeated(previous, msg))
+    }
+
+    /// Writes the cause starting at `column`, with its continuation lines indented to `indent`.
+    /// Returns its message, if it has a single one.
+    fn write(
+        self,
+        f: &mut core::fmt::Formatter<'_>,
+        column: usize,
+        indent: usize,
+        options: &RenderOptions,
+    ) -> Result<Option<Cow<'a, str>>, core::fmt::Error> {
+        match self {
+            Cause::Error(err) => {
+                let nested = NestedError {
+                    error: err,
+                    options: &RenderOptions {
+                        width: options.width.map(|width| width.saturating_sub(indent)),
+                        ..options.clone()
+                    },
+                    start: column - indent,
+                };
+                write!(Indented { out: f, indent }, "{}", nested)?;
+                Ok(None)
+            }
+            Cause::Group(group) => {
+                write_wrapped(f, &group.header(), column, options.width, "")?;
+                write_group_errors(f, group, indent, options)?;
+                Ok(None)
+            }
+            Cause::Message(msg) => {
+                write_wrapped(f, &msg, column, options.width, "")?;
+                Ok(Some(msg.into()))
+            }
+        }
+    }
+}
+
+/// Renders an [`Error`] nested in the chain of another one, starting at `start`.
+struct NestedError<'a> {
+    error: &'a Error,
+    options: &'a RenderOptions,
+    start: usize,
+}
+
+impl core::fmt::Display for NestedError<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        render(self.error, f, self.options, self.start)
+    }
+}
+
+/// Writes the errors of a `group` below its header, indented by `indent`, with `options`.
+fn write_group_errors(
+    f: &mut core::fmt::Formatter<'_>,
+    group: &ErrorGroup,
+    indent: usize,
+    options: &RenderOptions,
+) -> core::fmt::Result {
+    group.write_errors(f, indent, &mut |out, err| {
+        write!(
+            out,
+            "{}",
+            Rendered {
+                error: err,
+                options
+            }
+        )
+    })
+}
+
+/// Takes the message of the first cause out of `causes`, for an error or a frame without one,
+/// along with the group it heads, if any, whose errors follow the message. A nested error is
+/// left `pending`, to be rendered on its own.
+fn take_message<'a>(
+    causes: &mut Causes<'a>,
+    pending: &mut Option<Cause<'a>>,
+) -> Option<(Cow<'a, str>, Option<&'a ErrorGroup>)> {
+    match causes.next().map(Cause::new) {
+        Some(Cause::Message(msg)) => Some((msg.into(), None)),
+        Some(Cause::Group(group)) => Some((group.header().into(), Some(group))),
+        cause => {
+            *pending = cause;
+            None
+        }
+    }
+}
+
+/// Renders the error as a single headline, starting at `start` after the prefixes, followed by
+/// the messages of its causes.
+fn write_terse(
+    err: &Error,
+    f: &mut core::fmt::Formatter<'_>,
+    options: &RenderOptions,
+    start: usize,
+) -> core::fmt::Result {
+    let theme = options.theme.as_ref();
+    let style = theme.map_or("", |theme| theme.message);
+    let mut frames = err.inner.frames.iter().rev();
+    let head = frames.next().expect("an error has at least one frame");
+    let mut messages = frames.filter_map(crate::translate::message);
+    let mut causes = err.causes();
+    let mut pending = None;
+    let mut group = None;
+    // An error without a message of its own, such as one converted with `?`, is headed by the
+    // first message of its chain instead.
+    let headline = match crate::translate::message(head) {
+        Some(msg) => Some(msg),
+        None => messages.next().or_else(|| {
+            let (msg, errors) = take_message(&mut causes, &mut pending)?;
+            group = errors;
+            Some(msg)
+        }),
+    };
+    let mut previous = None;
+    if let Some(msg) = headline {
+        let column = write_wrapped(f, &msg, start, options.width, style)?;
+        write_location_after(f, &head.location, column, start, options)?;
+        previous = Some(msg);
+    } else {
+        write_location(f, &head.location, theme)?;
+    }
+    if let Some(group) = group {
+        write_group_errors(f, group, 0, options)?;
+    }
+    for msg in messages {
+        if is_repeated(previous.as_deref(), &msg) {
+            continue;
+        }
+        f.write_str("\n  ")?;
+        write_caused_by(f, theme)?;
+        write_wrapped(f, &msg, 2 + CAUSED_BY, options.width, style)?;
+        previous = Some(msg);
+    }
+    for cause in pending.into_iter().chain((&mut causes).map(Cause::new)) {
+        if cause.is_repeated(previous.as_deref()) {
+            continue;
+        }
+        f.write_str("\n  ")?;
+        write_caused_by(f, theme)?;
+        previous = cause.write(f, 2 + CAUSED_BY, 2, options)?;
+    }
+    if causes.is_truncated() {
+        f.write_str("\n  (further causes omitted)")?;
+    }
+    Ok(())
+}
+
+/// Renders every frame on its own, increasingly indented, line with its location. The first
+/// one starts at `start`, after the prefixes.
+fn write_verbose(
+    err: &Error,
+    f: &mut core::fmt::Formatter<'_>,
+    options: &RenderOptions,
+    start: usize,
+) -> core::fmt::Result {
+    let theme = options.theme.as_ref();
+    let style = theme.map_or("", |theme| theme.message);
+    let mut depth = 0;
+    let mut previous = None;
+    let mut causes = err.causes();
+    let mut pending = None;
+    let innermost = err.inner.frames.len() - 1;
+    for (i, frame) in err.inner.frames.iter().rev().enumerate() {
+        let mut message = crate::translate::message(frame);
+        let mut group = None;
+        // The frame of a conversion, such as `?`, is rendered with the message of its source.
+        if message.is_none() && i == innermost {
+            if let Some((msg, errors)) = take_message(&mut causes, &mut pending) {
+                message = Some(msg);
+                group = errors;
+            }
+        }
+        if message.is_none() && !options.locations {
+            continue;
+        }
+        let mut column = start;
+        if depth > 0 {
+            write!(f, "\n{:indent$}", "", indent = depth * 2)?;
+            write_caused_by(f, theme)?;
+            column = depth * 2 + CAUSED_BY;
+        }
+        // Frames are kept for their location, even if their message is repeated.
+        if let Some(msg) = message {
+            let end = write_wrapped(f, &msg, column, options.width, style)?;
+            write_location_after(f, &frame.location, end, column, options)?;
+            previous = Some(msg);
+        } else {
+            write_location(f, &frame.location, theme)?;
+        }
+        #[cfg(feature = "snippets")]
+        if options.snippets && options.locations {
+            write_snippet(f, &frame.location, depth * 2 + 2, theme)?;
+        }
+        if let Some(group) = group {
+            write_group_errors(f, group, depth * 2, options)?;
+        }
+        depth += 1;
+    }
+    for cause in pending.into_iter().chain((&mut causes).map(Cause::new)) {
+        if cause.is_repeated(previous.as_deref()) {
+            continue;
+        }
+        write!(f, "\n{:indent$}", "", indent = depth * 2)?;
+        write_caused_by(f, theme)?;
+        previous = cause.write(f, depth * 2 + CAUSED_BY, depth * 2, options)?;
+        depth += 1;
+    }
+    if causes.is_truncated() {
+        write!(
+            f,
+            "\n{:indent$}(further causes omitted)",
+            "",
+            indent = depth * 2
+        )?;
+    }
+    Ok(())
+}
+
+/// Writes the source line of `location` with a caret under its column, if the file can be read.
+#[cfg(feature = "snippets")]
+fn write_snippet(
+    f: &mut core::fmt::Formatter<'_>,
+    location: &Location,
+    indent: usize,
+    theme: Option<&Theme>,
+) -> core::fmt::Result {
+    use std::io::BufRead;
+
+    let Some(index) = (location.line() as usize).checked_sub(1) else {
+        return Ok(());
+    };
+    let Ok(file) = std::fs::File::open(location.file()) else {
+        return Ok(());
+    };
+    let Some(Ok(source)) = std::io::BufReader::new(file).lines().nth(index) else {
+        return Ok(());
+    };
+    // Keep tabs so the caret lines up with the source as the terminal renders it.
+    let offset: String = source
+        .chars()
+        .take((location.column() as usize).saturating_sub(1))
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let number = location.line().to_string();
+    let style = theme.map_or("", |theme| theme.location);
+    write!(f, "\n{:indent$}", "", indent = indent)?;
+    write_styled(f, style, format_args!("{} |", number))?;
+    write!(f, " {}\n{:indent$}", source, "", indent = indent)?;
+    write_styled(
+        f,
+        style,
+        format_args!("{:width$} |", "", width = number.len()),
+    )?;
+    write!(f, " {}^", offset)
+}
+
+/// Renders the fields, notes, help messages and URL that follow the chain.
+fn write_sections(err: &Error, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    for (key, value) in err.fields() {
+        write!(f, "\n  {} = {}", key, value)?;
+    }
+    for note in err.notes() {
+        write!(f, "\n  note: {}", note)?;
+    }
+    for help in err.helps() {
+        write!(f, "\n  help: {}", help)?;
+    }
+    if let Some(url) = err.url() {
+        write!(f, "\n  see: {}", url)?;
+    }
+    Ok(())
+}
+
+/// A custom renderer for [`Error`], see [`set_report_hook`].
+#[cfg(feature = "std")]
+type ReportHook = dyn Fn(&Error, &mut core::fmt::Formatter<'_>) -> core::fmt::Result + Send + Sync;
+
+#[cfg(feature = "std")]
+static REPORT_HOOK: std::sync::RwLock<Option<std::sync::Arc<ReportHook>>> =
+    std::sync::RwLock::new(None);
+
+/// Installs a hook that renders every [`Error`] instead of the built-in renderer.
+///
+/// The `Display` implementation of [`Error`] delegates to the hook, which can still call
+/// [`render_default`] to add to the default output. Installing a hook replaces the previous one.
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// fu::set_color(false);
+/// fu::set_report_hook(|err, f| {
+///     write!(f, "[myapp] ")?;
+///     fu::render_default(err, f)
+/// });
+/// assert!(error!("oops").to_string().starts_with("[myapp] oops"));
+/// ```
+#[cfg(feature = "std")]
+pub fn set_report_hook<F>(hook: F)
+where
+    F: Fn(&Error, &mut core::fmt::Formatter<'_>) -> core::fmt::Result + Send + Sync + 'static,
+{
+    let mut current = REPORT_HOOK.write().unwrap_or_else(|e| e.into_inner());
+    *current = Some(std::sync::Arc::new(hook));
+}
+
+/// Renders the error with the built-in renderer, ignoring any [report hook](set_report_hook).
+///
+/// The terse format shows the outermost location only, while the alternate format (`{:#}`)
+/// shows every frame with its own location. With the `json` feature, setting the `FU_STYLE`
+/// environment variable to `json` renders the error as a single JSON object instead, as
+/// serialized with `serde`.
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// # #[cfg(feature = "json")]
+/// # {
+/// // Usually set by whoever runs the program, before the first error is rendered.
+/// std::env::set_var("FU_STYLE", "json");
+/// let json: serde_json::Value = serde_json::from_str(&error!("oops").to_string()).unwrap();
+/// assert_eq!(json["message"], "oops");
+/// # }
+/// ```
+pub fn render_default(err: &Error, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    #[cfg(feature = "json")]
+    if env_style() == Some(EnvStyle::Json) {
+        let json = serde_json::to_string(err).map_err(|_| core::fmt::Error)?;
+        return f.write_str(&json);
+    }
+    let options = RenderOptions::new().verbose(f.alternate());
+    render(err, f, &options, 0)
+}
+
+/// Options to render an [`Error`] with, see [`Error::render_to`].
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// let options = RenderOptions::new().color(false).verbose(true).snippets(false);
+/// let mut out = Vec::new();
+/// error!("oops").render_to(&mut out, &options).unwrap();
+/// # #[cfg(not(fu_no_location))]
+/// assert!(String::from_utf8(out).unwrap().starts_with("oops    "));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderOptions {
+    theme: Option<Theme>,
+    verbose: bool,
+    width: Option<usize>,
+    #[cfg_attr(not(feature = "snippets"), allow(dead_code))]
+    snippets: bool,
+    #[cfg_attr(not(feature = "backtrace"), allow(dead_code))]
+    backtrace: bool,
+    locations: bool,
+    #[cfg_attr(
+        not(any(
+            feature = "id",
+            feature = "thread",
+            feature = "timestamp",
+            feature = "tracing"
+        )),
+        allow(dead_code)
+    )]
+    metadata: bool,
+}
+
+impl RenderOptions {
+    /// Returns the options used by `Display`: colors with the global [theme](set_theme) if
+    /// [enabled](color_enabled), the terse format, the global [wrap width](wrap_width), and
+    /// snippets and backtraces when their features are enabled.
+    pub fn new() -> Self {
+        RenderOptions {
+            theme: active_theme(),
+            verbose: false,
+            width: wrap_width(),
+            snippets: true,
+            backtrace: true,
+            locations: true,
+            metadata: true,
+        }
+    }
+
+    /// Returns deterministic options for snapshot tests, see [`Error::to_snapshot_string`].
+    ///
+    /// Errors are rendered in the verbose format without colors, wrapping, locations, frames
+    /// without a message, or anything that changes from one run to the next: the backtrace,
+    /// timestamp, thread, ID and span trace.
+    pub fn snapshot() -> Self {
+        RenderOptions {
+            theme: None,
+            verbose: true,
+            width: None,
+            snippets: false,
+            backtrace: false,
+            locations: false,
+            metadata: false,
+        }
+    }
+
+    /// Renders with colors in the global [theme](set_theme), or without any.
+    pub fn color(mut self, enabled: bool) -> Self {
+        self.theme = enabled.then(theme);
+        self
+    }
+
+    /// Renders with colors in the given `theme`.
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = Some(theme);
+        self
+    }
+
+    /// Renders every frame with its own location, like the alternate format (`{:#}`).
+    pub fn verbose(mut self, enabled: bool) -> Self {
+        self.verbose = enabled;
+        self
+    }
+
+    /// Wraps long messages at `width` columns, or disables wrapping with `None`.
+    pub fn width(mut self, width: Option<usize>) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Renders the source line of every location in the verbose format, with the `snippets`
+    /// feature.
+    pub fn snippets(mut self, enabled: bool) -> Self {
+        self.snippets = enabled;
+        self
+    }
+
+    /// Renders the backtrace captured with the error, with the `backtrace` feature.
+    pub fn backtrace(mut self, enabled: bool) -> Self {
+        self.backtrace = enabled;
+        self
+    }
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions::new()
+    }
+}
+
+/// Renders an [`Error`] with given [`RenderOptions`], regardless of the format flags and of any
+/// report hook.
+pub(crate) struct Rendered<'a> {
+    pub(crate) error: &'a Error,
+    pub(crate) options: &'a RenderOptions,
+}
+
+impl core::fmt::Display for Rendered<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        render(self.error, f, self.options, 0)
+    }
+}
+
+/// Renders the error with `options`, its first line starting at `column`.
+fn render(
+    err: &Error,
+    f: &mut core::fmt::Formatter<'_>,
+    options: &RenderOptions,
+    column: usize,
+) -> core::fmt::Result {
+    let mut start = column + write_severity(err, f, options.theme.as_ref())?;
+    if let Some(code) = &err.inner.code {
+        write!(f, "[{}] ", code)?;
+        start += code.chars().count() + 3;
+    }
+    if options.verbose {
+        write_verbose(err, f, options, start)?;
+    } else {
+        write_terse(err, f, options, start)?;
+    }
+    write_sections(err, f)?;
+    #[cfg(feature = "id")]
+    if options.metadata {
+        write!(f, "\n  id: {}", err.inner.id)?;
+    }
+    #[cfg(feature = "timestamp")]
+    if options.verbose && options.metadata {
+        write!(
+            f,
+            "\n  time: {}",
+            crate::timestamp::Rfc3339(err.inner.timestamp)
+        )?;
+    }
+    #[cfg(feature = "thread")]
+    if options.verbose && options.metadata {
+        let thread = &err.inner.thread;
+        let name = thread.name().unwrap_or("<unnamed>");
+        write!(f, "\n  thread: '{}' ({:?})", name, thread.id())?;
+    }
+    #[cfg(feature = "tracing")]
+    if options.verbose
+        && options.metadata
+        && err.inner.span_trace.status() == tracing_error::SpanTraceStatus::CAPTURED
+    {
+        write!(f, "\n\nSpan trace:\n{}", err.inner.span_trace)?;
+    }
+    #[cfg(feature = "backtrace")]
+    if options.backtrace
+        && err.inner.backtrace.status() == std::backtrace::BacktraceStatus::Captured
+    {
+        write!(f, "\n\nStack backtrace:\n{}", err.inner.backtrace)?;
+    }
+    Ok(())
+}
+
+/// Writes the `warning:` or `fatal:` prefix of an error, nothing for plain errors. Returns the
+/// width of the prefix.
+fn write_severity(
+    err: &Error,
+    f: &mut core::fmt::Formatter<'_>,
+    theme: Option<&Theme>,
+) -> Result<usize, core::fmt::Error> {
+    let style = match err.severity() {
+        Severity::Warning => theme.map_or("", |theme| theme.warning),
+        Severity::Error => return Ok(0),
+        Severity::Fatal => theme.map_or("", |theme| theme.fatal),
+    };
+    write_styled(f, style, format_args!("{}:", err.severity()))?;
+    f.write_str(" ")?;
+    Ok(err.severity().as_str().len() + 2)
+}
+
+/// Renders an [`Error`] with a given [`Theme`], see [`Error::themed`].
+#[derive(Debug)]
+pub struct Themed<'a> {
+    pub(crate) error: &'a Error,
+    pub(crate) theme: Theme,
+}
+
+impl core::fmt::Display for Themed<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let options = RenderOptions::new()
+            .theme(self.theme)
+            .verbose(f.alternate());
+        render(self.error, f, &options, 0)
+    }
+}
+
+/// Renders an [`Error`] and its causes on a single line, see [`Error::compact`].
+#[derive(Debug)]
+pub struct Compact<'a>(pub(crate) &'a Error);
+
+impl core::fmt::Display for Compact<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let err = self.0;
+        write_severity(err, f, None)?;
+        if let Some(code) = &err.inner.code {
+            write!(f, "[{}] ", code)?;
+        }
+        let mut separator = "";
+        for message in err.messages() {
+            write!(f, "{}{}", separator, message)?;
+            separator = ": ";
+        }
+        Ok(())
+    }
+}
+
+/// Renders the error with the installed [report hook](set_report_hook), or [`render_default`].
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// fu::set_color(false);
+/// let err = error!("inner").context("outer");
+/// let verbose = format!("{:#}", err);
+/// let cause = verbose.lines().find(|line| line.contains("Caused by:")).unwrap();
+/// assert!(cause.starts_with("  Caused by: inner"));
+/// # #[cfg(not(fu_no_location))]
+/// assert!(cause.contains(file!()));
+/// ```
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        #[cfg(feature = "std")]
+        {
+            // Release the lock before rendering, the hook may render other errors.
+            let hook = REPORT_HOOK
+                .read()
+                .unwrap_or_else(|e| e.into_inner())
+                .clone();
+            if let Some(hook) = hook {
+                return hook(self, f);
+            }
+        }
+        render_default(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{error, Wrap};
+
+    #[test]
+    fn test_short_path() {
+        assert_eq!(short_path("src/lib.rs"), "src/lib.rs");
+        assert_eq!(
+            short_path("/home/me/.cargo/registry/src/index.crates.io-6f17d22bba15001f/serde-1.0.0/src/de.rs"),
+            "serde-1.0.0/src/de.rs"
+        );
+        assert_eq!(
+            short_path(
+                "C:\\Users\\me\\.cargo\\registry\\src\\index.crates.io-1\\dep-0.1.0\\src\\lib.rs"
+            ),
+            "dep-0.1.0\\src\\lib.rs"
+        );
+        assert_eq!(
+            short_path("/home/me/.cargo/git/checkouts/dep-1a2b3c/abc1234/src/lib.rs"),
+            "dep-1a2b3c/abc1234/src/lib.rs"
+        );
+
+        let cwd = std::env::current_dir().unwrap();
+        let file = format!("{}/src/main.rs", cwd.display());
+        assert_eq!(short_path(&file), "src/main.rs");
+    }
+
+    #[test]
+    fn test_remap() {
+        let remaps = [
+            ("/home/ci".to_string(), "<home>".to_string()),
+            ("/home/ci/build".to_string(), "<src>".to_string()),
+        ];
+        assert_eq!(
+            remap(&remaps, "/home/ci/build/src/main.rs").as_deref(),
+            Some("<src>/src/main.rs")
+        );
+        assert_eq!(
+            remap(&remaps, "/home/ci/.cargo/x.rs").as_deref(),
+            Some("<home>/.cargo/x.rs")
+        );
+        assert_eq!(remap(&remaps, "src/main.rs"), None);
+    }
+
+    #[test]
+    fn test_location_format() {
+        struct Formatted(&'static str, Option<u32>);
+
+        impl core::fmt::Display for Formatted {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                write_location_format(f, self.0, "src/main.rs", 10, self.1)
+            }
+        }
+
+        assert_eq!(
+            Formatted("{file}:{line}:{column}", Some(5)).to_string(),
+            "src/main.rs:10:5"
+        );
+        assert_eq!(
+            Formatted("{file}({line},{col})", Some(5)).to_string(),
+            "src/main.rs(10,5)"
+        );
+        assert_eq!(
+            Formatted("at {file} {unknown} {", Some(5)).to_string(),
+            "at src/main.rs {unknown} {"
+        );
+        assert_eq!(
+            Formatted("{file}:{line}:{column}", None).to_string(),
+            "src/main.rs:10"
+        );
+        assert_eq!(
+            Formatted("{file}({line},{col})", None).to_string(),
+            "src/main.rs(10)"
+        );
+    }
+
+    #[test]
+    fn test_repeated_messages() {
+        set_color(false);
+        let io = std::io::Error::other("no such file");
+        let err = error!("failed to read config: no such file").chain(io);
+        let rendered = err.to_string();
+        assert!(!rendered.contains("Caused by:"));
+        let verbose = format!("{:#}", err);
+        assert!(!verbose.contains("Caused by:"));
+
+        let err = error!("no such file").context("failed to read config: no such file");
+        assert_eq!(err.to_string().matches("no such file").count(), 1);
+        assert_eq!(
+            format!("{:#}", err)
+                .matches("Caused by: no such file")
+                .count(),
+            1
+        );
+
+        let err = error!("outer").chain(std::io::Error::other("inner"));
+        assert!(err.to_string().contains("Caused by: inner"));
+
+        let err = error!("index x").chain(std::io::Error::other("x"));
+        assert!(err.to_string().contains("Caused by: x"));
+        let err = error!("read config:no such file").chain(std::io::Error::other("no such file"));
+        assert!(err.to_string().contains("Caused by: no such file"));
+    }
+
+    #[cfg(not(fu_no_location))]
+    #[test]
+    fn test_themed() {
+        let theme = Theme {
+            message: "<m>",
+            location: "<l>",
+            caused_by: "<c>",
+            warning: "<w>",
+            fatal: "<f>",
+        };
+        let err = crate::error!("inner").context("outer");
+        let rendered = err.themed(theme).to_string();
+        let mut lines = rendered.lines();
+        assert!(lines.next().unwrap().starts_with("<m>outer\x1b[0m    <l>"));
+        assert_eq!(
+            lines.next().unwrap(),
+            "  <c>Caused by:\x1b[0m <m>inner\x1b[0m"
+        );
+
+        let err = err.with_severity(crate::Severity::Fatal);
+        let rendered = err.themed(theme).to_string();
+        assert!(rendered.starts_with("<f>fatal:\x1b[0m <m>outer"));
+        assert!(err.compact().to_string().starts_with("fatal: outer"));
+    }
+
+    #[test]
+    fn test_parse_style() {
+        assert_eq!(parse_style("plain"), Some(EnvStyle::Plain));
+        assert_eq!(parse_style(" Color\n"), Some(EnvStyle::Color));
+        assert_eq!(parse_style("JSON"), Some(EnvStyle::Json));
+        assert_eq!(parse_style("fancy"), None);
+        assert_eq!(parse_style(""), None);
+    }
+
+    #[test]
+    fn test_unknown_location() {
+        let err = Error::new(Some("inner"), Location::UNKNOWN).context("outer");
+        let plain = RenderOptions::new().color(false).verbose(true);
+        let mut out = Vec::new();
+        err.render_to(&mut out, &plain).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.lines().any(|line| line == "  Caused by: inner"));
+        assert_eq!(Location::UNKNOWN.to_string(), "<unknown>");
+    }
+
+    #[cfg(fu_no_location)]
+    #[test]
+    fn test_no_location() {
+        assert_eq!(core::mem::size_of::<Location>(), 0);
+        let err = error!("oops").context("outer");
+        assert!(!err.location().is_known());
+        let rendered = err.themed(Theme::PLAIN).to_string();
+        assert!(rendered.starts_with("outer\n  Caused by: oops"));
+        let verbose = format!("{:#}", err.themed(Theme::PLAIN));
+        assert!(verbose.starts_with("outer\n  Caused by: oops"));
+        assert_eq!(err.compact().to_string(), "outer: oops");
+    }
+
+    #[cfg(not(fu_no_location))]
+    #[test]
+    fn test_snapshot() {
+        let err = error!("inner")
+            .context("middle")
+            .chain(std::io::Error::other("io"))
+            .context("outer")
+            .with_code("E7")
+            .with_severity(Severity::Warning)
+            .note("retry later");
+        assert_eq!(
+            err.to_snapshot_string(),
+            "warning: [E7] outer\n  Caused by: middle\n    Caused by: inner\n      Caused by: io\n  note: retry later"
+        );
+
+        fn read() -> crate::Result<()> {
+            Err(std::io::Error::other("disk on fire"))?
+        }
+        let err = read().unwrap_err();
+        assert_eq!(err.to_snapshot_string(), "disk on fire");
+        let verbose = format!("{:#}", err.themed(Theme::PLAIN));
+        assert!(verbose.starts_with("disk on fire    "));
+        assert!(!verbose.contains("Caused by:"));
+
+        let err = read().unwrap_err().context("failed to load");
+        assert_eq!(
+            err.to_snapshot_string(),
+            "failed to load\n  Caused by: disk on fire"
+        );
+        let err = Err::<(), _>(error!("inner")).loc().unwrap_err();
+        assert_eq!(err.to_snapshot_string(), "inner");
+
+        // Nested errors are rendered with the same options, not with their own `Display`.
+        let err = error!("outer")
+            .chain(core::fmt::Error)
+            .chain(error!("inner").context("middle"));
+        assert_eq!(
+            err.to_snapshot_string(),
+            format!(
+                "outer\n  Caused by: {}\n    Caused by: middle\n      Caused by: inner",
+                core::fmt::Error
+            )
+        );
+    }
+
+    #[cfg(not(fu_no_location))]
+    #[test]
+    fn test_render_to() {
+        let err = error!("inner").context("outer");
+        let render = |options: &RenderOptions| {
+            let mut out = Vec::new();
+            err.render_to(&mut out, options).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        let plain = RenderOptions::new().color(false);
+        let terse = render(&plain);
+        assert!(terse.starts_with("outer    "));
+        assert!(terse.contains("\n  Caused by: inner"));
+        assert!(!terse.contains('\x1b'));
+
+        let verbose = render(&plain.clone().verbose(true).snippets(false));
+        assert!(verbose.contains("\n  Caused by: inner    "));
+        assert!(!verbose.contains(" | "));
+
+        let themed = render(&plain.theme(Theme::DEFAULT));
+        assert!(themed.contains(Theme::DEFAULT.location));
+    }
+
+    #[test]
+    fn test_render_group() {
+        fn validate() -> crate::Result<()> {
+            let group = crate::ErrorGroup::from(vec![
+                error!("name is empty"),
+                error!("inner").context("age is negative"),
+            ]);
+            group.into_result()?;
+            Ok(())
+        }
+        let render = |err: &Error, options: &RenderOptions| {
+            let mut out = Vec::new();
+            err.render_to(&mut out, options).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        let plain = RenderOptions::new().color(false);
+
+        let err = validate().unwrap_err();
+        let terse = render(&err, &plain);
+        assert!(terse.starts_with("2 errors occurred:"));
+        assert!(terse.contains("\n  1. name is empty"));
+        assert!(terse.contains("\n  2. age is negative"));
+        assert!(terse.contains("\n       Caused by: inner"));
+        assert!(!terse.contains('\x1b'));
+
+        let err = err.context("invalid form");
+        let terse = render(&err, &plain);
+        assert!(terse.contains("\n  Caused by: 2 errors occurred:\n    1. name is empty"));
+        assert!(!terse.contains('\x1b'));
+        assert!(!render(&err, &plain.verbose(true)).contains('\x1b'));
+    }
+
+    #[cfg(not(fu_no_location))]
+    #[test]
+    fn test_wrap() {
+        let render = |err: &Error, options: &RenderOptions| {
+            let mut out = Vec::new();
+            err.render_to(&mut out, options).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        let message =
+            "invalid type: string \"abc\", expected a sequence of at least three integers";
+        let err = crate::Error::new(Some(message), Location::new("src/main.rs", 1, 1))
+            .chain(std::io::Error::other(message.replace("integers", "bytes")));
+        let options = RenderOptions::new().color(false).width(Some(60));
+
+        let terse = render(&err, &options);
+        let mut lines = terse.lines();
+        assert_eq!(
+            lines.next().unwrap(),
+            "invalid type: string \"abc\", expected a sequence of at least"
+        );
+        assert_eq!(lines.next().unwrap(), "three integers    src/main.rs:[1:1]");
+        assert_eq!(
+            lines.next().unwrap(),
+            "  Caused by: invalid type: string \"abc\", expected a sequence"
+        );
+        assert_eq!(
+            lines.next().unwrap(),
+            "             of at least three bytes"
+        );
+
+        let err = crate::Error::new(
+            Some("short"),
+            Location::new("src/a/long/path/to/main.rs", 1, 1),
+        )
+        .context("outer");
+        let verbose = render(&err, &options.clone().verbose(true).width(Some(40)));
+        assert!(
+            verbose.contains("\n  Caused by: short\n             src/a/long/path/to/main.rs:[1:1]")
+        );
+
+        let unwrapped = render(&err, &options.width(None));
+        assert_eq!(unwrapped.lines().nth(1).unwrap(), "  Caused by: short");
+    }
+
+    #[cfg(feature = "thread")]
+    #[test]
+    fn test_thread() {
+        let err = std::thread::spawn(|| crate::error!("oops")).join().unwrap();
+        let id = format!("{:?}", err.thread().id());
+        assert!(format!("{:#}", err).contains(&format!("\n  thread: '<unnamed>' ({})", id)));
+        assert!(!err.to_string().contains("thread: "));
+    }
+
+    #[test]
+    fn test_compact() {
+        let err = "x"
+            .parse::<i32>()
+            .wrap("parse")
+            .unwrap_err()
+            .context("load")
+            .with_code("E42");
+        assert_eq!(
+            err.compact().to_string(),
+            "[E42] load: parse: invalid digit found in string"
+        );
+    }
+
+    #[cfg(feature = "snippets")]
+    #[cfg(not(fu_no_location))]
+    #[test]
+    fn test_snippet() {
+        let err = crate::error!("oops");
+        let line = err.location().line();
+        assert!(!err.themed(Theme::PLAIN).to_string().contains(" | "));
+
+        let verbose = format!("{:#}", err.themed(Theme::PLAIN));
+        let width = line.to_string().len();
+        let mut lines = verbose.lines().skip(1);
+        assert_eq!(
+            lines.next().unwrap(),
+            format!("  {} |         let err = crate::error!(\"oops\");", line)
+        );
+        assert_eq!(
+            lines.next().unwrap(),
+            format!("  {:width$} | {:18}^", "", "", width = width)
+        );
+    }
+}
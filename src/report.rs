@@ -0,0 +1,174 @@
+//! Reporting errors at the top of a program: [`Report`] for `fn main()` and [`exit`].
+
+use std::process::{ExitCode, Termination};
+
+use crate::{Error, Result};
+
+/// The outcome of a program, printing the full error chain to stderr on failure.
+///
+/// Returning a [`Result`] from `main` prints the error with `Debug`, prefixed with `Error: `.
+/// A `Report` instead prints every frame with its location (the alternate `{:#}` format) and
+/// exits with the [exit code](Error::with_exit_code) of the error, or `1` if there is none.
+///
+/// # Example
+/// ```should_panic
+/// # use fu::*;
+/// fn run() -> Result<()> {
+///     bail!("something went wrong")
+/// }
+///
+/// fn main() -> fu::Report {
+///     run().into()
+/// }
+/// ```
+pub struct Report(Result<()>);
+
+impl Report {
+    /// Returns the error of this report, if the program failed.
+    pub fn error(&self) -> Option<&Error> {
+        self.0.as_ref().err()
+    }
+}
+
+impl From<Result<()>> for Report {
+    fn from(result: Result<()>) -> Self {
+        Report(result)
+    }
+}
+
+impl From<Error> for Report {
+    fn from(err: Error) -> Self {
+        Report(Err(err))
+    }
+}
+
+/// Propagates errors with `?` and `do yeet` in a function returning a `Report`, on nightly.
+///
+/// # Example
+/// ```should_panic
+/// #![feature(yeet_expr)]
+/// # use fu::*;
+/// fn main() -> fu::Report {
+///     let port: u16 = "8080".parse()?;
+///     if port < 1024 {
+///         do yeet error!("port {} is reserved", port);
+///     }
+///     do yeet "not implemented"
+/// }
+/// ```
+#[cfg(feature = "nightly")]
+impl core::ops::Try for Report {
+    type Output = ();
+    type Residual = Result<core::convert::Infallible>;
+
+    fn from_output((): ()) -> Self {
+        Report(Ok(()))
+    }
+
+    fn branch(self) -> core::ops::ControlFlow<Self::Residual> {
+        match self.0 {
+            Ok(()) => core::ops::ControlFlow::Continue(()),
+            Err(err) => core::ops::ControlFlow::Break(Err(err)),
+        }
+    }
+}
+
+#[cfg(feature = "nightly")]
+impl<E: Into<Error>> core::ops::FromResidual<core::result::Result<core::convert::Infallible, E>>
+    for Report
+{
+    #[cfg_attr(not(fu_no_location), track_caller)]
+    fn from_residual(residual: core::result::Result<core::convert::Infallible, E>) -> Self {
+        match residual {
+            Err(err) => Report(Err(err.into())),
+        }
+    }
+}
+
+#[cfg(feature = "nightly")]
+impl<E: Into<Error>> core::ops::FromResidual<core::ops::Yeet<E>> for Report {
+    #[cfg_attr(not(fu_no_location), track_caller)]
+    fn from_residual(core::ops::Yeet(err): core::ops::Yeet<E>) -> Self {
+        Report(Err(err.into()))
+    }
+}
+
+impl Termination for Report {
+    fn report(self) -> ExitCode {
+        match self.0 {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(err) => {
+                eprint_report(&err);
+                ExitCode::from(err.exit_code().unwrap_or(1))
+            }
+        }
+    }
+}
+
+/// Prints the error to stderr and terminates the process with its exit code, or `1` if there
+/// is none.
+///
+/// # Example
+/// ```no_run
+/// # use fu::*;
+/// fn run() -> Result<()> {
+///     bail!("missing argument")
+/// }
+///
+/// if let Err(err) = run() {
+///     fu::exit(err.with_exit_code(2));
+/// }
+/// ```
+pub fn exit(err: Error) -> ! {
+    eprint_report(&err);
+    std::process::exit(err.exit_code().unwrap_or(1).into())
+}
+
+fn eprint_report(err: &Error) {
+    err.reported();
+    eprintln!("Error: {:#}", err);
+}
+
+impl std::fmt::Debug for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.0 {
+            Ok(()) => write!(f, "Ok"),
+            Err(err) => write!(f, "{:#}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error;
+
+    #[test]
+    fn test_report_exit_code() {
+        assert_eq!(Report::from(Ok(())).report(), ExitCode::SUCCESS);
+        assert_eq!(Report::from(error!("oops")).report(), ExitCode::FAILURE);
+        assert_eq!(
+            Report::from(error!("usage").with_exit_code(2)).report(),
+            ExitCode::from(2)
+        );
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn test_try_report() {
+        use core::ops::{FromResidual, Yeet};
+
+        fn run(input: &str) -> Report {
+            let value: u8 = input.parse()?;
+            if value == 0 {
+                // What `do yeet` expands to, which is gated in the parser even when unused.
+                return Report::from_residual(Yeet(error!("zero").with_exit_code(3)));
+            }
+            Report::from(Ok(()))
+        }
+
+        assert!(run("1").error().is_none());
+        assert!(run("x").error().unwrap().is::<core::num::ParseIntError>());
+        assert_eq!(run("0").report(), ExitCode::from(3));
+    }
+}
@@ -0,0 +1,152 @@
+//! [Sentry](sentry_core) support: reporting errors as Sentry events.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use sentry_core::protocol::{Event, Exception, Frame, Level, Mechanism, Stacktrace, Value};
+use sentry_core::types::Uuid;
+
+use crate::{Error, Severity};
+
+static CAPTURE_REPORTED: AtomicBool = AtomicBool::new(false);
+
+/// Captures every error reported by [`Report`](crate::Report), [`exit`](crate::exit) or
+/// `Error::log` with [`Error::capture`], or stops doing so.
+///
+/// Reported errors are not captured by default.
+///
+/// # Example
+/// ```
+/// fu::set_capture_reported(true);
+/// ```
+pub fn set_capture_reported(enabled: bool) {
+    CAPTURE_REPORTED.store(enabled, Ordering::Relaxed);
+}
+
+/// Captures a reported error, if enabled.
+pub(crate) fn capture_reported(err: &Error) {
+    if CAPTURE_REPORTED.load(Ordering::Relaxed) {
+        err.capture();
+    }
+}
+
+/// Sends the event of `err` to the current Sentry hub.
+pub(crate) fn capture(err: &Error) -> Uuid {
+    sentry_core::capture_event(event(err))
+}
+
+/// Builds the Sentry event of `err`, see [`Error::sentry_event`].
+pub(crate) fn event(err: &Error) -> Event<'static> {
+    let causes: Vec<_> = err.causes().collect();
+    let mut exceptions: Vec<_> = causes
+        .into_iter()
+        .rev()
+        .map(|cause| {
+            let debug = format!("{:?}", cause);
+            let value = cause.to_string();
+            let ty = if debug == format!("{:?}", value) {
+                "Error"
+            } else {
+                sentry_core::parse_type_from_debug(&debug)
+            };
+            Exception {
+                ty: ty.into(),
+                value: Some(value),
+                ..Default::default()
+            }
+        })
+        .collect();
+    exceptions.extend(err.frames().rev().map(|frame| {
+        let location = frame.location();
+        let known = location.is_known();
+        let frame = Frame {
+            function: location.function().map(Into::into),
+            filename: Some(location.file().into()),
+            lineno: Some(location.line().into()),
+            colno: Some(location.column().into()),
+            ..Default::default()
+        };
+        Exception {
+            ty: "fu::Error".into(),
+            value: None,
+            stacktrace: known.then(|| Stacktrace {
+                frames: vec![frame],
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }));
+    for (exception, frame) in exceptions.iter_mut().rev().zip(err.frames()) {
+        exception.value = frame.message().map(Into::into);
+    }
+    if let Some(outermost) = exceptions.last_mut() {
+        outermost.mechanism = Some(Mechanism {
+            ty: "fu".into(),
+            handled: Some(true),
+            ..Default::default()
+        });
+        #[cfg(feature = "backtrace")]
+        if err.backtrace().status() == std::backtrace::BacktraceStatus::Captured {
+            outermost.raw_stacktrace =
+                sentry_backtrace::parse_stacktrace(&err.backtrace().to_string());
+        }
+    }
+
+    let mut event = Event {
+        exception: exceptions.into(),
+        level: match err.severity() {
+            Severity::Warning => Level::Warning,
+            Severity::Error => Level::Error,
+            Severity::Fatal => Level::Fatal,
+        },
+        ..Default::default()
+    };
+    for (key, value) in err.fields() {
+        event.extra.insert(key.into(), Value::String(value.into()));
+    }
+    if let Some(code) = err.code() {
+        event.tags.insert("code".into(), code.into());
+    }
+    if let Some(kind) = err.kind() {
+        event.tags.insert("kind".into(), kind.to_string());
+    }
+    event
+}
+
+#[cfg(all(test, not(fu_no_location)))]
+mod tests {
+    use ::sentry_core::protocol::Level;
+
+    use crate::*;
+
+    #[test]
+    fn test_sentry_event() {
+        let line = line!() + 1;
+        let err = error!(std::fmt::Error)
+            .context("failed to render")
+            .with("template", "index.html")
+            .with_code("E7")
+            .with_severity(Severity::Warning);
+        let event = err.sentry_event();
+
+        let exceptions = &event.exception.values;
+        let types: Vec<_> = exceptions.iter().map(|e| e.ty.as_str()).collect();
+        assert_eq!(types, ["Error", "fu::Error", "fu::Error"]);
+        let values: Vec<_> = exceptions.iter().map(|e| e.value.as_deref()).collect();
+        assert_eq!(
+            values,
+            [
+                Some(std::fmt::Error.to_string().as_str()),
+                None,
+                Some("failed to render")
+            ]
+        );
+        let frame = &exceptions[1].stacktrace.as_ref().unwrap().frames[0];
+        assert_eq!(frame.filename.as_deref(), Some(file!()));
+        assert_eq!(frame.lineno, Some(line.into()));
+        assert_eq!(exceptions[2].mechanism.as_ref().unwrap().ty, "fu");
+
+        assert_eq!(event.level, Level::Warning);
+        assert_eq!(event.tags["code"], "E7");
+        assert_eq!(event.extra["template"], "index.html");
+    }
+}
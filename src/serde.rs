@@ -0,0 +1,440 @@
+//! [`serde`] support for [`Error`] and the [`DeserializedError`] it round-trips into.
+
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::error::Error as StdError;
+
+use serde::de::{MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{render, Error, Frame, Location};
+
+#[derive(Serialize)]
+struct SerLocation<'a> {
+    file: Cow<'a, str>,
+    line: u32,
+    column: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    function: Option<&'a str>,
+}
+
+impl From<&Location> for SerLocation<'_> {
+    fn from(location: &Location) -> Self {
+        SerLocation {
+            file: render::remapped(location.file()),
+            line: location.line(),
+            column: location.column(),
+            function: location.function(),
+        }
+    }
+}
+
+/// Returns the location to serialize, `None` if it is unknown.
+fn known(location: &Location) -> Option<SerLocation<'_>> {
+    location.is_known().then(|| location.into())
+}
+
+impl<'a> From<&'a WireLocation> for SerLocation<'a> {
+    fn from(location: &'a WireLocation) -> Self {
+        SerLocation {
+            file: Cow::Borrowed(&location.file),
+            line: location.line,
+            column: location.column,
+            function: location.function.as_deref(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct SerCause<'a> {
+    message: Option<Cow<'a, str>>,
+    location: Option<SerLocation<'a>>,
+}
+
+#[derive(Serialize)]
+struct SerError<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<&'a str>,
+    message: Option<&'a str>,
+    location: Option<SerLocation<'a>>,
+    chain: Vec<SerCause<'a>>,
+    #[serde(skip_serializing_if = "Fields::is_empty")]
+    fields: Fields<'a>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    notes: &'a [String],
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    help: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<&'a str>,
+}
+
+/// Key-value fields, serialized as a map in insertion order.
+struct Fields<'a>(&'a [(String, String)]);
+
+impl Fields<'_> {
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl Serialize for Fields<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|(k, v)| (k, v)))
+    }
+}
+
+fn deserialize_fields<'de, D>(deserializer: D) -> Result<Vec<(String, String)>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct FieldsVisitor;
+
+    impl<'de> Visitor<'de> for FieldsVisitor {
+        type Value = Vec<(String, String)>;
+
+        fn expecting(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            write!(f, "a map of fields")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut fields = Vec::with_capacity(map.size_hint().unwrap_or(0));
+            while let Some(field) = map.next_entry()? {
+                fields.push(field);
+            }
+            Ok(fields)
+        }
+    }
+
+    deserializer.deserialize_map(FieldsVisitor)
+}
+
+/// Appends `source` and everything below it to `chain`, keeping the location of errors that
+/// were themselves deserialized, and flattening the frames of nested errors into it.
+fn push_sources<'a>(
+    chain: &mut Vec<SerCause<'a>>,
+    sources: impl Iterator<Item = &'a (dyn StdError + 'static)>,
+) {
+    for err in sources {
+        if let Some(nested) = err.downcast_ref::<Error>() {
+            push_frames(chain, nested.inner.frames.iter().rev());
+            push_sources(chain, nested.causes());
+            continue;
+        }
+        match err.downcast_ref::<DeserializedError>() {
+            Some(remote) => chain.push(SerCause {
+                message: remote.message().map(Cow::Borrowed),
+                location: remote.location.as_ref().map(Into::into),
+            }),
+            None => chain.push(SerCause {
+                message: crate::cause_message(err),
+                location: None,
+            }),
+        }
+    }
+}
+
+/// Appends the frames to `chain` with their location, those without a message included.
+fn push_frames<'a>(chain: &mut Vec<SerCause<'a>>, frames: impl Iterator<Item = &'a Frame>) {
+    chain.extend(frames.map(|frame| SerCause {
+        message: frame.message().map(Cow::Borrowed),
+        location: known(&frame.location),
+    }));
+}
+
+/// Serializes the outermost message and location, followed by every cause, outermost first.
+///
+/// Context frames keep their location, and those without a message, like the ones added by
+/// [`loc`](crate::Wrap::loc), have a `null` message. Foreign sources are rendered with `Display`.
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// let err = error!("connection refused").context("failed to fetch");
+/// let json = serde_json::to_value(&err).unwrap();
+/// assert_eq!(json["message"], "failed to fetch");
+/// assert_eq!(json["chain"][0]["message"], "connection refused");
+/// ```
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut frames = self.inner.frames.iter().rev();
+        let head = frames.next().expect("an error has at least one frame");
+        let mut chain = Vec::new();
+        push_frames(&mut chain, frames);
+        push_sources(&mut chain, self.causes());
+
+        SerError {
+            code: self.code(),
+            message: head.message(),
+            location: known(&head.location),
+            chain,
+            fields: Fields(&self.inner.fields),
+            notes: &self.inner.notes,
+            help: &self.inner.help,
+            url: self.url(),
+        }
+        .serialize(serializer)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+struct WireLocation {
+    file: String,
+    line: u32,
+    column: u32,
+    #[serde(default)]
+    function: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct WireCause {
+    message: Option<String>,
+    location: Option<WireLocation>,
+}
+
+#[derive(Deserialize)]
+struct WireError {
+    #[serde(default)]
+    code: Option<String>,
+    message: Option<String>,
+    location: Option<WireLocation>,
+    chain: Vec<WireCause>,
+    #[serde(default, deserialize_with = "deserialize_fields")]
+    fields: Vec<(String, String)>,
+    #[serde(default)]
+    notes: Vec<String>,
+    #[serde(default)]
+    help: Vec<String>,
+    #[serde(default)]
+    url: Option<String>,
+}
+
+/// An error chain reconstructed from the serialized form of an [`Error`].
+///
+/// Every cause is itself a `DeserializedError`, available through [`StdError::source`], so the
+/// message and location of every frame produced on the other side is preserved. It can be
+/// used as the source of a local [`Error`] with [`Error::chain`].
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// # use std::error::Error as _;
+/// let err = error!("connection refused").context("failed to fetch");
+/// let json = serde_json::to_string(&err).unwrap();
+///
+/// let remote: DeserializedError = serde_json::from_str(&json).unwrap();
+/// assert_eq!(remote.message(), Some("failed to fetch"));
+/// # #[cfg(not(fu_no_location))]
+/// assert_eq!(remote.location().unwrap().0, file!());
+///
+/// let cause = remote.source().unwrap();
+/// assert!(cause.to_string().starts_with("connection refused"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeserializedError {
+    code: Option<String>,
+    message: Option<String>,
+    location: Option<WireLocation>,
+    source: Option<Box<DeserializedError>>,
+    fields: Vec<(String, String)>,
+    notes: Vec<String>,
+    help: Vec<String>,
+    url: Option<String>,
+}
+
+impl DeserializedError {
+    /// Returns the code of the original error, if any.
+    pub fn code(&self) -> Option<&str> {
+        self.code.as_deref()
+    }
+
+    /// Returns the message of this frame, if any.
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    /// Returns the file, line and column this frame was created at, if known.
+    pub fn location(&self) -> Option<(&str, u32, u32)> {
+        self.location
+            .as_ref()
+            .map(|l| (l.file.as_str(), l.line, l.column))
+    }
+
+    /// Returns the path of the function this frame was created in, if known.
+    pub fn function(&self) -> Option<&str> {
+        self.location.as_ref()?.function.as_deref()
+    }
+
+    /// Returns the documentation URL of the original error, if any.
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+
+    /// Returns an iterator over the notes of the original error.
+    pub fn notes(&self) -> impl Iterator<Item = &str> {
+        self.notes.iter().map(String::as_str)
+    }
+
+    /// Returns an iterator over the help messages of the original error.
+    pub fn helps(&self) -> impl Iterator<Item = &str> {
+        self.help.iter().map(String::as_str)
+    }
+
+    /// Returns an iterator over the key-value fields of the original error.
+    pub fn fields(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.fields.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+impl From<WireError> for DeserializedError {
+    fn from(wire: WireError) -> Self {
+        let source = wire.chain.into_iter().rev().fold(None, |source, cause| {
+            Some(Box::new(DeserializedError {
+                code: None,
+                message: cause.message,
+                location: cause.location,
+                source,
+                fields: Vec::new(),
+                notes: Vec::new(),
+                help: Vec::new(),
+                url: None,
+            }))
+        });
+        DeserializedError {
+            code: wire.code,
+            message: wire.message,
+            location: wire.location,
+            source,
+            fields: wire.fields,
+            notes: wire.notes,
+            help: wire.help,
+            url: wire.url,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for DeserializedError {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        WireError::deserialize(deserializer).map(Into::into)
+    }
+}
+
+impl Serialize for DeserializedError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut chain = Vec::new();
+        let sources = core::iter::successors(self.source(), |&err| err.source());
+        push_sources(&mut chain, sources);
+        SerError {
+            code: self.code(),
+            message: self.message.as_deref(),
+            location: self.location.as_ref().map(Into::into),
+            chain,
+            fields: Fields(&self.fields),
+            notes: &self.notes,
+            help: &self.help,
+            url: self.url(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl core::fmt::Display for DeserializedError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if let Some(msg) = &self.message {
+            write!(f, "{}", msg)?;
+        }
+        if let Some(location) = &self.location {
+            if self.message.is_some() {
+                write!(f, "    ")?;
+            }
+            render::write_file_location(
+                f,
+                &location.file,
+                location.line,
+                location.column,
+                location.function.as_deref(),
+                render::active_theme().as_ref(),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl StdError for DeserializedError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.source
+            .as_deref()
+            .map(|e| e as &(dyn StdError + 'static))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[cfg(not(fu_no_location))]
+    #[test]
+    fn test_serialize_chain() {
+        let err = "x".parse::<i32>().wrap("parse").unwrap_err();
+        let line = line!() - 1;
+        let json = serde_json::to_value(&err).unwrap();
+
+        assert_eq!(json["message"], "parse");
+        assert_eq!(json["location"]["file"], file!());
+        assert_eq!(json["location"]["line"], line);
+        assert_eq!(json["chain"][0]["message"], "invalid digit found in string");
+        assert!(json["chain"][0]["location"].is_null());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let err = "x"
+            .parse::<i32>()
+            .wrap("parse")
+            .unwrap_err()
+            .context("load")
+            .with("path", "app.toml")
+            .with_code("E42")
+            .note("while loading")
+            .help("check the path")
+            .see("https://example.com/config");
+        let json = serde_json::to_value(&err).unwrap();
+
+        let remote: DeserializedError = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&remote).unwrap(), json);
+        assert_eq!(json["fields"]["path"], "app.toml");
+        assert_eq!(remote.code(), Some("E42"));
+        assert_eq!(remote.notes().collect::<Vec<_>>(), ["while loading"]);
+        assert_eq!(remote.helps().collect::<Vec<_>>(), ["check the path"]);
+        assert_eq!(remote.url(), Some("https://example.com/config"));
+        assert_eq!(remote.fields().collect::<Vec<_>>(), [("path", "app.toml")]);
+
+        let messages: Vec<_> = std::iter::successors(Some(&remote), |e| e.source.as_deref())
+            .map(|e| e.message().unwrap())
+            .collect();
+        assert_eq!(messages, ["load", "parse", "invalid digit found in string"]);
+
+        // Wrapping a remote error on this side keeps the remote locations in the chain.
+        let local = error!("rpc failed").chain(remote);
+        let cause = &serde_json::to_value(&local).unwrap()["chain"][0];
+        assert_eq!(cause["message"], "load");
+        assert_eq!(cause["location"], json["location"]);
+    }
+
+    #[test]
+    fn test_round_trip_without_message() {
+        let err = Err::<(), _>(error!("inner"))
+            .loc()
+            .unwrap_err()
+            .context("outer");
+        let json = serde_json::to_value(&err).unwrap();
+        assert!(json["chain"][0]["message"].is_null());
+        assert_eq!(json["chain"][1]["message"], "inner");
+
+        let remote: DeserializedError = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&remote).unwrap(), json);
+        let local = error!("rpc failed").chain(remote);
+        assert_eq!(local.compact().to_string(), "rpc failed: outer: inner");
+    }
+}
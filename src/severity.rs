@@ -0,0 +1,42 @@
+//! [`Severity`], how serious an error is.
+
+/// How serious an error is, see [`Error::with_severity`](crate::Error::with_severity).
+///
+/// Errors are [`Severity::Error`] by default. Warnings and fatal errors are rendered with a
+/// `warning:` or `fatal:` prefix, styled by the [`Theme`](crate::Theme).
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// fu::set_color(false);
+/// let err = error!("unused import").with_severity(Severity::Warning);
+/// assert!(err.to_string().starts_with("warning: unused import"));
+/// assert!(Severity::Warning < Severity::Fatal);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// A recoverable issue that does not stop the operation.
+    Warning,
+    /// An operation failed.
+    #[default]
+    Error,
+    /// A failure that the program cannot recover from.
+    Fatal,
+}
+
+impl Severity {
+    /// Returns the name of the severity, e.g. `warning`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+            Severity::Fatal => "fatal",
+        }
+    }
+}
+
+impl core::fmt::Display for Severity {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
@@ -0,0 +1,94 @@
+//! The time errors are created at, with the `timestamp` feature.
+
+use std::sync::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+static CLOCK: RwLock<fn() -> SystemTime> = RwLock::new(SystemTime::now);
+
+/// Sets the clock that timestamps errors when they are created, [`SystemTime::now`] by default.
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// use std::time::{Duration, SystemTime, UNIX_EPOCH};
+///
+/// fu::set_clock(|| UNIX_EPOCH + Duration::from_secs(86_400));
+/// assert_eq!(error!("oops").timestamp(), UNIX_EPOCH + Duration::from_secs(86_400));
+/// fu::set_clock(SystemTime::now);
+/// ```
+pub fn set_clock(clock: fn() -> SystemTime) {
+    *CLOCK.write().unwrap_or_else(|e| e.into_inner()) = clock;
+}
+
+/// Returns the current time according to the clock set with [`set_clock`].
+pub(crate) fn now() -> SystemTime {
+    let clock = *CLOCK.read().unwrap_or_else(|e| e.into_inner());
+    clock()
+}
+
+/// Renders a time in UTC as RFC 3339 with milliseconds, e.g. `2024-05-01T12:30:00.000Z`.
+pub(crate) struct Rfc3339(pub(crate) SystemTime);
+
+impl core::fmt::Display for Rfc3339 {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let since_epoch = self.0.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let secs = since_epoch.as_secs();
+        let (year, month, day) = civil_from_days(secs / 86_400);
+        let secs_of_day = secs % 86_400;
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+            year,
+            month,
+            day,
+            secs_of_day / 3600,
+            secs_of_day / 60 % 60,
+            secs_of_day % 60,
+            since_epoch.subsec_millis()
+        )
+    }
+}
+
+/// Converts days since 1970-01-01 to a Gregorian (year, month, day).
+///
+/// See <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_rfc3339() {
+        let at = |secs: u64, millis: u64| {
+            Rfc3339(UNIX_EPOCH + Duration::from_millis(secs * 1000 + millis))
+        };
+        assert_eq!(at(0, 0).to_string(), "1970-01-01T00:00:00.000Z");
+        assert_eq!(at(951_782_400, 5).to_string(), "2000-02-29T00:00:00.005Z");
+        assert_eq!(
+            at(1_714_566_600, 250).to_string(),
+            "2024-05-01T12:30:00.250Z"
+        );
+    }
+
+    #[test]
+    fn test_render_timestamp() {
+        let err = crate::error!("inner").context("outer");
+        let time = Rfc3339(err.timestamp()).to_string();
+        assert!(format!("{:#}", err).contains(&format!("\n  time: {}", time)));
+        assert!(!err.to_string().contains("time: "));
+    }
+}
@@ -0,0 +1,107 @@
+//! [`tonic`] support: [`Error`] as a gRPC [`Status`].
+
+use tonic::{Code, Status};
+
+use crate::{Error, ErrorKind};
+
+/// Returns the gRPC code named by the [code](Error::with_code) of an error, e.g. `NOT_FOUND`.
+fn named_code(code: &str) -> Option<Code> {
+    let code = match code {
+        "CANCELLED" => Code::Cancelled,
+        "UNKNOWN" => Code::Unknown,
+        "INVALID_ARGUMENT" => Code::InvalidArgument,
+        "DEADLINE_EXCEEDED" => Code::DeadlineExceeded,
+        "NOT_FOUND" => Code::NotFound,
+        "ALREADY_EXISTS" => Code::AlreadyExists,
+        "PERMISSION_DENIED" => Code::PermissionDenied,
+        "RESOURCE_EXHAUSTED" => Code::ResourceExhausted,
+        "FAILED_PRECONDITION" => Code::FailedPrecondition,
+        "ABORTED" => Code::Aborted,
+        "OUT_OF_RANGE" => Code::OutOfRange,
+        "UNIMPLEMENTED" => Code::Unimplemented,
+        "INTERNAL" => Code::Internal,
+        "UNAVAILABLE" => Code::Unavailable,
+        "DATA_LOSS" => Code::DataLoss,
+        "UNAUTHENTICATED" => Code::Unauthenticated,
+        _ => return None,
+    };
+    Some(code)
+}
+
+/// Returns the gRPC code closest in meaning to an HTTP status, if any.
+fn status_code(status: u16) -> Option<Code> {
+    let code = match status {
+        400 => Code::InvalidArgument,
+        401 => Code::Unauthenticated,
+        403 => Code::PermissionDenied,
+        404 => Code::NotFound,
+        409 => Code::AlreadyExists,
+        412 => Code::FailedPrecondition,
+        429 => Code::ResourceExhausted,
+        499 => Code::Cancelled,
+        500 => Code::Internal,
+        501 => Code::Unimplemented,
+        503 => Code::Unavailable,
+        504 => Code::DeadlineExceeded,
+        _ => return None,
+    };
+    Some(code)
+}
+
+/// Returns the gRPC code of an error kind, if any.
+fn kind_code(kind: ErrorKind) -> Option<Code> {
+    let code = match kind {
+        ErrorKind::NotFound => Code::NotFound,
+        ErrorKind::InvalidInput => Code::InvalidArgument,
+        ErrorKind::Timeout => Code::DeadlineExceeded,
+        ErrorKind::Permission => Code::PermissionDenied,
+        ErrorKind::Internal => Code::Internal,
+        _ => return None,
+    };
+    Some(code)
+}
+
+/// Maps the error to a gRPC status, with the messages of the whole chain, see
+/// [`Error::compact`].
+///
+/// The gRPC code is taken from the [code](Error::with_code) of the error if it names one, e.g.
+/// `NOT_FOUND`, then from its [HTTP status](Error::with_status), then from its
+/// [kind](Error::kind), and is `INTERNAL` otherwise.
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// let err = error!("no such user").context("failed to get user").with_code("NOT_FOUND");
+/// let status = tonic::Status::from(err);
+/// assert_eq!(status.code(), tonic::Code::NotFound);
+/// assert_eq!(status.message(), "[NOT_FOUND] failed to get user: no such user");
+/// ```
+impl From<Error> for Status {
+    fn from(err: Error) -> Self {
+        let code = err
+            .code()
+            .and_then(named_code)
+            .or_else(|| err.status().and_then(status_code))
+            .or_else(|| err.kind().and_then(kind_code))
+            .unwrap_or(Code::Internal);
+        Status::new(code, err.compact().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::tonic::{Code, Status};
+
+    use crate::*;
+
+    #[test]
+    fn test_status_code() {
+        assert_eq!(Status::from(error!("oops")).code(), Code::Internal);
+        let err = error!("slow down").with_status(429);
+        assert_eq!(Status::from(err).code(), Code::ResourceExhausted);
+        let err = error!("oops").with_status(418).with_code("E42");
+        assert_eq!(Status::from(err).code(), Code::Internal);
+        let err = error!("too slow").with_kind(ErrorKind::Timeout);
+        assert_eq!(Status::from(err).code(), Code::DeadlineExceeded);
+    }
+}
@@ -0,0 +1,52 @@
+//! [`tracing`] support: span traces captured with every [`Error`] and construction events.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::Error;
+
+static EVENTS: AtomicBool = AtomicBool::new(false);
+
+/// Emits a `tracing::error!` event every time an [`Error`] is created, or stops doing so.
+///
+/// The event carries the compact rendering of the error as its message, see
+/// [`Error::compact`], and the location it was created at in a `location` field. Events are
+/// disabled by default.
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// fu::set_trace_events(true);
+/// let err = error!("oops"); // emits ERROR oops location=...
+/// ```
+pub fn set_trace_events(enabled: bool) {
+    EVENTS.store(enabled, Ordering::Relaxed);
+}
+
+/// Emits the construction event for `err`, if enabled.
+pub(crate) fn emit_event(err: &Error) {
+    if EVENTS.load(Ordering::Relaxed) {
+        tracing::error!(location = %err.location(), "{}", err.compact());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_error::ErrorLayer;
+    use tracing_subscriber::prelude::*;
+
+    use crate::*;
+
+    #[test]
+    fn test_span_trace() {
+        let subscriber = tracing_subscriber::registry().with(ErrorLayer::default());
+        let err = tracing::subscriber::with_default(subscriber, || {
+            let _span = tracing::info_span!("load_config", path = "app.toml").entered();
+            error!("oops")
+        });
+
+        let verbose = format!("{:#}", err);
+        assert!(verbose.contains("\n\nSpan trace:\n"));
+        assert!(verbose.contains("load_config"));
+        assert!(!err.to_string().contains("Span trace:"));
+    }
+}
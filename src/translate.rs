@@ -0,0 +1,124 @@
+//! Message keys, to translate the messages of errors when they are rendered.
+
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::Frame;
+
+/// A stable identifier of a message with its arguments, attached next to the English text with
+/// [`Error::with_message_key`](crate::Error::with_message_key).
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// let key = MessageKey::new("config.not_found").with("path", "app.toml");
+/// assert_eq!(key.key(), "config.not_found");
+/// assert_eq!(key.args().collect::<Vec<_>>(), [("path", "app.toml")]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageKey {
+    key: Cow<'static, str>,
+    args: Vec<(String, String)>,
+}
+
+impl MessageKey {
+    /// Creates a key without arguments.
+    pub fn new<K: Into<Cow<'static, str>>>(key: K) -> Self {
+        MessageKey {
+            key: key.into(),
+            args: Vec::new(),
+        }
+    }
+
+    /// Adds a named argument to substitute in the translated message.
+    pub fn with<K, V>(mut self, name: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: core::fmt::Display,
+    {
+        self.args.push((name.into(), value.to_string()));
+        self
+    }
+
+    /// Returns the key.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Returns an iterator over the arguments, in insertion order.
+    pub fn args(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.args.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+#[cfg(feature = "std")]
+type Translator = dyn Fn(&MessageKey) -> Option<String> + Send + Sync;
+
+#[cfg(feature = "std")]
+static TRANSLATOR: std::sync::RwLock<Option<std::sync::Arc<Translator>>> =
+    std::sync::RwLock::new(None);
+
+/// Installs a function that translates the messages of frames with a [`MessageKey`] when errors
+/// are rendered.
+///
+/// Frames whose key the translator returns `None` for, and frames without a key, are rendered
+/// with their original message. Installing a translator replaces the previous one.
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// fu::set_color(false);
+/// fu::set_translator(|key| match key.key() {
+///     "user.not_found" => {
+///         let (_, name) = key.args().next()?;
+///         Some(format!("utilisateur introuvable : {}", name))
+///     }
+///     _ => None,
+/// });
+///
+/// let key = MessageKey::new("user.not_found").with("name", "bob");
+/// let err = error!("user not found: bob").with_message_key(key);
+/// assert!(err.to_string().starts_with("utilisateur introuvable : bob"));
+/// assert_eq!(err.message(), Some("user not found: bob"));
+/// ```
+#[cfg(feature = "std")]
+pub fn set_translator<F>(translator: F)
+where
+    F: Fn(&MessageKey) -> Option<String> + Send + Sync + 'static,
+{
+    let mut current = TRANSLATOR.write().unwrap_or_else(|e| e.into_inner());
+    *current = Some(std::sync::Arc::new(translator));
+}
+
+/// Returns the message of a frame to render, translated if it has a key.
+pub(crate) fn message(frame: &Frame) -> Option<Cow<'_, str>> {
+    #[cfg(feature = "std")]
+    if let Some(key) = &frame.key {
+        let translator = TRANSLATOR.read().unwrap_or_else(|e| e.into_inner()).clone();
+        if let Some(translated) = translator.and_then(|translate| translate(key)) {
+            return Some(Cow::Owned(translated));
+        }
+    }
+    frame.message().map(Cow::Borrowed)
+}
+
+#[cfg(all(test, not(fu_no_location)))]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_translate() {
+        set_translator(|key| match key.key() {
+            "test.outer" => Some("äußere".into()),
+            _ => None,
+        });
+        let err = error!("inner")
+            .with_message_key(MessageKey::new("test.inner"))
+            .context("outer")
+            .with_message_key(MessageKey::new("test.outer"));
+        assert_eq!(err.compact().to_string(), "äußere: inner");
+        assert!(format!("{:#}", err).starts_with("äußere    "));
+        assert_eq!(err.message(), Some("outer"));
+    }
+}
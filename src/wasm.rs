@@ -0,0 +1,41 @@
+//! [`wasm_bindgen`] support: [`Error`] as a JavaScript `Error`.
+
+use js_sys::Reflect;
+use wasm_bindgen::JsValue;
+
+use crate::Error;
+
+/// Converts the error into a JavaScript `Error` whose message has the messages of the whole
+/// chain, see [`Error::compact`].
+///
+/// The location of the error is set as `file`, `line` and `column` properties, and its
+/// [code](Error::with_code), if any, as a `code` property.
+///
+/// # Example
+/// ```no_run
+/// # use fu::*;
+/// # use wasm_bindgen::JsValue;
+/// fn parse(input: &str) -> core::result::Result<u32, JsValue> {
+///     let n = input.parse().wrap("invalid number")?;
+///     Ok(n)
+/// }
+/// ```
+impl From<Error> for JsValue {
+    fn from(err: Error) -> Self {
+        let js = js_sys::Error::new(&err.compact().to_string());
+        let location = err.location();
+        let properties = location.is_known().then(|| {
+            [
+                ("file", JsValue::from_str(location.file())),
+                ("line", JsValue::from(location.line())),
+                ("column", JsValue::from(location.column())),
+            ]
+        });
+        let code = err.code().map(|code| ("code", JsValue::from_str(code)));
+        for (key, value) in properties.into_iter().flatten().chain(code) {
+            // Setting a property on a fresh `Error` object cannot fail.
+            let _ = Reflect::set(&js, &JsValue::from_str(key), &value);
+        }
+        js.into()
+    }
+}
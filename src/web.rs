@@ -0,0 +1,133 @@
+//! Shared pieces of the web framework integrations.
+
+use alloc::string::String;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use crate::Error;
+
+/// The format of the body of error responses, see [`set_body_format`].
+///
+/// An error without a message of its own, like a `?` conversion, has the reason of its status
+/// as message instead, e.g. `Internal Server Error`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyFormat {
+    /// A JSON object with the code of the error, if any, and its outermost message. This is the
+    /// default.
+    Json,
+    /// The outermost message of the error, as plain text.
+    Text,
+}
+
+const JSON: u8 = 0;
+const TEXT: u8 = 1;
+
+static BODY_FORMAT: AtomicU8 = AtomicU8::new(JSON);
+
+/// Sets the format of the body of error responses globally.
+///
+/// The causes of the error are left out in every format, they are usually not meant for
+/// clients.
+///
+/// # Example
+/// ```
+/// # use fu::*;
+/// fu::set_body_format(BodyFormat::Text);
+/// ```
+pub fn set_body_format(format: BodyFormat) {
+    let value = match format {
+        BodyFormat::Json => JSON,
+        BodyFormat::Text => TEXT,
+    };
+    BODY_FORMAT.store(value, Ordering::Relaxed);
+}
+
+/// Returns the format of the body of error responses.
+pub fn body_format() -> BodyFormat {
+    match BODY_FORMAT.load(Ordering::Relaxed) {
+        TEXT => BodyFormat::Text,
+        _ => BodyFormat::Json,
+    }
+}
+
+/// Returns the attached status of `err` if it is a valid HTTP status code, or `500`.
+pub(crate) fn status_code(err: &Error) -> u16 {
+    err.status()
+        .filter(|status| (100..1000).contains(status))
+        .unwrap_or(500)
+}
+
+/// Returns the outermost message of `err`, or the reason of its status if it has none. The
+/// message of a source is not used, it is usually not meant for clients either.
+fn message(err: &Error) -> &str {
+    err.message().unwrap_or_else(|| reason(status_code(err)))
+}
+
+/// Returns the reason phrase of common HTTP status codes, and `Error` for the others.
+fn reason(status: u16) -> &'static str {
+    match status {
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        409 => "Conflict",
+        410 => "Gone",
+        413 => "Payload Too Large",
+        415 => "Unsupported Media Type",
+        422 => "Unprocessable Entity",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        501 => "Not Implemented",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "Error",
+    }
+}
+
+/// Returns the content type and the body of a response for `err`, see [`BodyFormat`].
+pub(crate) fn body(err: &Error) -> (&'static str, String) {
+    match body_format() {
+        BodyFormat::Json => ("application/json", json_body(err)),
+        BodyFormat::Text => ("text/plain; charset=utf-8", message(err).into()),
+    }
+}
+
+fn json_body(err: &Error) -> String {
+    let mut body = serde_json::Map::new();
+    if let Some(code) = err.code() {
+        body.insert("code".into(), code.into());
+    }
+    body.insert("message".into(), message(err).into());
+    serde_json::Value::Object(body).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    #[test]
+    fn test_response_parts() {
+        let err = error!("user not found").with_status(404).with_code("E404");
+        assert_eq!(status_code(&err), 404);
+        assert_eq!(
+            json_body(&err),
+            r#"{"code":"E404","message":"user not found"}"#
+        );
+
+        let err = error!("inner").context("internal").with_status(1000);
+        assert_eq!(status_code(&err), 500);
+        assert_eq!(json_body(&err), r#"{"message":"internal"}"#);
+
+        fn read() -> Result<()> {
+            Err(std::io::Error::other("/etc/secret: permission denied"))?;
+            Ok(())
+        }
+        let err = read().unwrap_err();
+        assert_eq!(json_body(&err), r#"{"message":"Internal Server Error"}"#);
+        let err = err.with_status(404);
+        assert_eq!(json_body(&err), r#"{"message":"Not Found"}"#);
+    }
+}